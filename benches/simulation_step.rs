@@ -0,0 +1,153 @@
+//! Measures the time per simulation tick with 1k and 10k agents, on a single thread and on all cores.
+//!
+//! Run with `cargo bench --bench simulation_step`. Every measurement runs in its own process since the
+//! size of the `ComputeTaskPool` can only be set once.
+
+use std::{
+    env,
+    process::Command,
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{app::PluginGroup, log::LogPlugin, prelude::*};
+use ecsmos_v2::{
+    plugins::{
+        default::plugin::ECSMosHeadlessPlugins,
+        scenario_loader::{
+            models::{AgentDescription, AreaDescription, ObjectiveDescription, ObstacleDescription, Scenario, ShapeDescription},
+            plugin::ScenarioLoaderPlugin,
+        },
+        social_foces_model::configuration::*,
+    },
+    scenarios::built_in::BuiltInScenario,
+};
+
+const AGENT_COUNTS: [usize; 2] = [1_000, 10_000];
+const WARMUP_TICKS: u32 = 5;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    match (flag(&args, "--threads"), flag(&args, "--agents")) {
+        (Some(threads), Some(agents)) => {
+            let time = measure(threads, agents);
+            println!("{}", time.as_secs_f64());
+        }
+        _ => compare(),
+    }
+}
+
+fn flag(args: &[String], name: &str) -> Option<usize> {
+    let position = args.iter().position(|a| a == name)?;
+    args.get(position + 1)?.parse().ok()
+}
+
+fn compare() {
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let executable = env::current_exe().expect("Could not find the benchmark executable");
+
+    println!("{:>8} {:>16} {:>16} {:>8}", "agents", "1 thread (ms)", format!("{} threads (ms)", cores), "speedup");
+
+    for agents in AGENT_COUNTS {
+        let run = |threads: usize| -> f64 {
+            let output = Command::new(&executable)
+                .args(["--threads", &threads.to_string(), "--agents", &agents.to_string()])
+                .output()
+                .expect("Could not run the benchmark");
+
+            String::from_utf8_lossy(&output.stdout)
+                .trim()
+                .parse::<f64>()
+                .expect("Invalid benchmark output")
+                * 1000.
+        };
+
+        let serial = run(1);
+        let parallel = run(cores);
+
+        println!("{:>8} {:>16.2} {:>16.2} {:>7.2}x", agents, serial, parallel, serial / parallel);
+    }
+}
+
+/// Average duration of a simulation tick
+fn measure(threads: usize, agents: usize) -> Duration {
+    let mut app = App::new();
+
+    app.add_plugins(
+        ECSMosHeadlessPlugins
+            .build()
+            .disable::<LogPlugin>()
+            .set(TaskPoolPlugin {
+                task_pool_options: TaskPoolOptions::with_num_threads(threads),
+            }),
+    )
+    .add_plugins(ScenarioLoaderPlugin { scenario: crowd_scenario(agents) });
+
+    for _ in 0..WARMUP_TICKS {
+        app.update();
+    }
+
+    let ticks = (100_000 / agents).clamp(10, 100) as u32;
+    let start = Instant::now();
+
+    for _ in 0..ticks {
+        app.update();
+    }
+
+    start.elapsed() / ticks
+}
+
+/// Agents laid out on a grid between two objectives, with a row of pillars in the way
+fn crowd_scenario(agents: usize) -> Scenario {
+    let mut scenario = BuiltInScenario::Corridor.load().expect("Invalid built in scenario");
+
+    let columns = (agents as f32).sqrt().ceil() as usize;
+    let spacing = 0.8;
+    let extent = columns as f32 * spacing;
+    let size = extent + 20.;
+
+    scenario.seed = Some(0);
+    scenario.simulation_area = AreaDescription { center: [0., 0.], size: [size, size] };
+    scenario.flow_field.cell_size = 0.5;
+    scenario.spawners.clear();
+
+    scenario.social_forces.forces = ForceConfiguration {
+        motivation_force: MotivationForceComputationStrategy::FlowFieldPathFinding,
+        repulsion_force: RepulsionForceComputationStrategy::SpatialHash,
+        obstacle_force: ObstacleForceComputationStrategy::EdgeGrid,
+    };
+
+    scenario.objectives = vec![
+        ObjectiveDescription { name: "right".into(), ordering: 0, position: [size / 2. - 2., 0.], shape: ShapeDescription::Circle(2.), service: None },
+        ObjectiveDescription { name: "left".into(), ordering: 1, position: [-size / 2. + 2., 0.], shape: ShapeDescription::Circle(2.), service: None },
+    ];
+
+    scenario.obstacles = (0..8)
+        .map(|i| ObstacleDescription {
+            position: [0., -extent / 2. + i as f32 * extent / 7.],
+            shape: ShapeDescription::Polygon(vec![[-0.5, -0.5], [0.5, -0.5], [0.5, 0.5], [-0.5, 0.5]]),
+        })
+        .collect();
+
+    scenario.agents = (0..agents)
+        .map(|i| {
+            let (column, row) = (i % columns, i / columns);
+
+            AgentDescription {
+                position: [
+                    column as f32 * spacing - extent / 2.,
+                    row as f32 * spacing - extent / 2.,
+                ],
+                radius: 0.25,
+                speed: [0., 0.],
+                destination: if column % 2 == 0 { "right" } else { "left" }.into(),
+                itinerary: Vec::new(),
+                desired_speed: None,
+                mass: None,
+            }
+        })
+        .collect();
+
+    scenario
+}
@@ -0,0 +1,358 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use ecsmos_v2::{
+    plugins::{
+        auto_end_simulation::resources::StopCondition, movement_tracking::configuration::ExportFormat,
+        report::resources::ReportFormat,
+        scenario_loader::models::Scenario,
+    },
+    scenarios::built_in::BuiltInScenario,
+};
+
+/// Exit code used when the arguments or the scenario are invalid
+pub const EXIT_INVALID_INPUT: u8 = 2;
+
+/// Pedestrian dynamics simulation based on the social forces model.
+///
+/// Exits with 0 on success, 2 when the arguments or the scenario are invalid, 3 when the
+/// tracking output could not be written and with the code reported by the simulation when it fails.
+#[derive(Parser, Debug)]
+#[command(version)]
+pub struct Cli {
+    /// Built-in scenario to run
+    #[arg(long, value_enum, default_value_t = BuiltInScenario::NarrowOpening)]
+    pub scenario: BuiltInScenario,
+
+    /// Scenario file (.ron or .json) to run instead of a built-in scenario
+    #[arg(long, conflicts_with = "scenario")]
+    pub scenario_file: Option<PathBuf>,
+
+    /// Run without a window or renderer
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Number of seconds the simulation is advanced by for each iteration (s)
+    #[arg(long)]
+    pub time_step: Option<f32>,
+
+    /// Length of the side of each cell of the flow field grid (m)
+    #[arg(long)]
+    pub cell_size: Option<f32>,
+
+    /// Seed of the random number generator, runs with the same seed and scenario are identical
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Simulated time after which the simulation ends (s)
+    #[arg(long)]
+    pub max_duration: Option<f32>,
+
+    /// Real time after which the simulation ends (s)
+    #[arg(long)]
+    pub max_wall_clock_time: Option<f32>,
+
+    /// Number of agents reaching their final objective after which the simulation ends
+    #[arg(long)]
+    pub max_arrivals: Option<u32>,
+
+    /// End the simulation when the mean agent speed stays below this value (m/s)
+    #[arg(long)]
+    pub gridlock_speed: Option<f32>,
+
+    /// Time the mean agent speed has to stay below `--gridlock-speed` (s)
+    #[arg(long, default_value_t = 10., requires = "gridlock_speed")]
+    pub gridlock_duration: f32,
+
+    /// Path of the tracking output, `{time}` is replaced by the start time of the run
+    #[arg(long)]
+    pub out: Option<String>,
+
+    /// File format of the tracking output, its extension is added to `--out` when missing
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    pub export_format: ExportFormat,
+
+    /// Write a summary report next to the tracking output when the simulation ends
+    #[arg(long, value_enum)]
+    pub report: Option<ReportFormat>,
+
+    /// Desired speed of the agents (m/s)
+    #[arg(long)]
+    pub desired_speed: Option<f32>,
+
+    /// Mass of the agents (kg)
+    #[arg(long)]
+    pub mass: Option<f32>,
+
+    /// Social force model constant A (N)
+    #[arg(long)]
+    pub a: Option<f32>,
+
+    /// Social force model constant B (m)
+    #[arg(long)]
+    pub b: Option<f32>,
+
+    /// Social force model body compression constant k (kg/s²)
+    #[arg(long)]
+    pub k: Option<f32>,
+
+    /// Social force model sliding friction constant kappa (kg/(m s))
+    #[arg(long)]
+    pub kappa: Option<f32>,
+}
+
+impl Cli {
+    /// Loads the selected scenario and applies the parameters overridden on the command line
+    pub fn load_scenario(&self) -> anyhow::Result<Scenario> {
+        let mut scenario = match &self.scenario_file {
+            Some(path) => Scenario::from_file(path)?,
+            None => self.scenario.load()?,
+        };
+
+        if self.seed.is_some() {
+            scenario.seed = self.seed;
+        }
+
+        let social_forces = &mut scenario.social_forces;
+
+        override_value(&mut scenario.simulation_time_step, self.time_step);
+        override_value(&mut scenario.flow_field.cell_size, self.cell_size);
+        override_value(&mut social_forces.agent_desired_speed, self.desired_speed);
+        override_value(&mut social_forces.agent_mass, self.mass);
+        override_value(&mut social_forces.a, self.a);
+        override_value(&mut social_forces.b, self.b);
+        override_value(&mut social_forces.k, self.k);
+        override_value(&mut social_forces.kappa, self.kappa);
+
+        // The values given on the command line also replace the distributions of the spawned agents
+        for parameters in scenario.spawners.iter_mut().filter_map(|spawner| spawner.agent_parameters.as_mut()) {
+            if self.desired_speed.is_some() {
+                parameters.desired_speed = None;
+            }
+
+            if self.mass.is_some() {
+                parameters.mass = None;
+            }
+        }
+
+        scenario.validate()?;
+
+        Ok(scenario)
+    }
+
+    /// Stop conditions of the scenario, or the default ones, followed by the ones given on the command line
+    pub fn stop_conditions(&self, scenario: &Scenario) -> Vec<StopCondition> {
+        let mut conditions = match scenario.stop_conditions.is_empty() {
+            true => vec![StopCondition::SpawnersFinishedAndNoAgents],
+            false => scenario.stop_conditions.clone(),
+        };
+
+        conditions.extend(self.max_duration.map(StopCondition::MaxSimulatedTime));
+        conditions.extend(self.max_wall_clock_time.map(StopCondition::MaxWallClockTime));
+        conditions.extend(self.max_arrivals.map(StopCondition::Arrivals));
+        conditions.extend(self.gridlock_speed.map(|speed| StopCondition::Gridlock {
+            speed,
+            duration: self.gridlock_duration,
+        }));
+
+        conditions
+    }
+}
+
+fn override_value<T>(value: &mut T, new_value: Option<T>) {
+    if let Some(new_value) = new_value {
+        *value = new_value;
+    }
+}
+
+// #######
+// Testing
+// #######
+
+#[cfg(test)]
+fn parse(args: &[&str]) -> Cli {
+    Cli::try_parse_from(std::iter::once("ecsmos-v2").chain(args.iter().copied())).expect("Invalid arguments")
+}
+
+#[cfg(test)]
+fn parse_error_code(args: &[&str]) -> i32 {
+    Cli::try_parse_from(std::iter::once("ecsmos-v2").chain(args.iter().copied()))
+        .expect_err("Arguments should be rejected")
+        .exit_code()
+}
+
+#[test]
+fn check_scenario_values_are_kept_without_overrides() {
+
+    // Act
+
+    let scenario = parse(&[]).load_scenario().unwrap();
+
+    // Assert
+
+    let built_in = BuiltInScenario::NarrowOpening.load().unwrap();
+
+    assert_eq!(scenario.simulation_time_step, built_in.simulation_time_step);
+    assert_eq!(scenario.flow_field.cell_size, built_in.flow_field.cell_size);
+    assert_eq!(scenario.social_forces.agent_desired_speed, built_in.social_forces.agent_desired_speed);
+    assert_eq!(scenario.seed, built_in.seed);
+}
+
+#[test]
+fn check_command_line_overrides_the_scenario() {
+
+    // Setup
+
+    let cli = parse(&[
+        "--scenario", "corridor",
+        "--seed", "7",
+        "--time-step", "0.1",
+        "--cell-size", "0.5",
+        "--desired-speed", "1.5",
+        "--mass", "70",
+        "--a", "2000",
+        "--b", "0.08",
+        "--k", "120000",
+        "--kappa", "240000",
+    ]);
+
+    // Act
+
+    let scenario = cli.load_scenario().unwrap();
+
+    // Assert
+
+    let social_forces = &scenario.social_forces;
+
+    // The corridor is twice as wide as the narrow opening room
+    assert_eq!(scenario.simulation_area.size, [42., 21.]);
+    assert_eq!(scenario.seed, Some(7));
+    assert_eq!(scenario.simulation_time_step, 0.1);
+    assert_eq!(scenario.flow_field.cell_size, 0.5);
+    assert_eq!(social_forces.agent_desired_speed, 1.5);
+    assert_eq!(social_forces.agent_mass, 70.);
+    assert_eq!(social_forces.a, 2000.);
+    assert_eq!(social_forces.b, 0.08);
+    assert_eq!(social_forces.k, 120000.);
+    assert_eq!(social_forces.kappa, 240000.);
+}
+
+#[test]
+fn check_invalid_overrides_are_rejected() {
+
+    // Act
+
+    let negative_time_step = parse(&["--time-step=-0.2"]).load_scenario();
+    let zero_cell_size = parse(&["--cell-size", "0"]).load_scenario();
+
+    // Assert
+
+    assert!(negative_time_step.is_err());
+    assert!(zero_cell_size.is_err());
+}
+
+#[test]
+fn check_invalid_scenario_files_are_rejected() {
+
+    // Setup
+
+    let directory = std::env::temp_dir().join(format!("ecsmos-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let unparsable = directory.join("unparsable.ron");
+    std::fs::write(&unparsable, "(simulation_area: ").unwrap();
+
+    let unsupported = directory.join("scenario.toml");
+    std::fs::write(&unsupported, "").unwrap();
+
+    let missing = directory.join("missing.ron");
+
+    // Act
+
+    let results = [&unparsable, &unsupported, &missing]
+        .map(|path| parse(&["--scenario-file", path.to_str().unwrap()]).load_scenario().is_err());
+
+    // Assert
+
+    assert_eq!(results, [true, true, true]);
+
+    std::fs::remove_dir_all(directory).ok();
+}
+
+#[test]
+fn check_invalid_arguments_exit_with_invalid_input_code() {
+
+    // Assert
+
+    let code = EXIT_INVALID_INPUT as i32;
+
+    assert_eq!(parse_error_code(&["--scenario", "corridor", "--scenario-file", "a.ron"]), code);
+    assert_eq!(parse_error_code(&["--scenario", "unknown"]), code);
+    assert_eq!(parse_error_code(&["--export-format", "xml"]), code);
+    assert_eq!(parse_error_code(&["--gridlock-duration", "5"]), code);
+    assert_eq!(parse_error_code(&["--time-step", "fast"]), code);
+}
+
+#[test]
+fn check_stop_conditions_from_the_command_line_follow_the_scenario_ones() {
+
+    // Setup
+
+    let cli = parse(&["--max-duration", "60", "--max-arrivals", "10", "--gridlock-speed", "0.1"]);
+    let mut scenario = cli.load_scenario().unwrap();
+
+    // Act
+
+    let defaults = cli.stop_conditions(&scenario);
+
+    scenario.stop_conditions = vec![StopCondition::MaxWallClockTime(5.)];
+    let from_scenario = cli.stop_conditions(&scenario);
+
+    // Assert
+
+    let from_command_line = [
+        StopCondition::MaxSimulatedTime(60.),
+        StopCondition::Arrivals(10),
+        StopCondition::Gridlock { speed: 0.1, duration: 10. },
+    ];
+
+    assert_eq!(defaults[0], StopCondition::SpawnersFinishedAndNoAgents);
+    assert_eq!(defaults[1..], from_command_line);
+    assert_eq!(from_scenario[0], StopCondition::MaxWallClockTime(5.));
+    assert_eq!(from_scenario[1..], from_command_line);
+}
+
+#[test]
+fn check_command_line_speed_and_mass_apply_to_spawned_agents() {
+    use bevy::{app::{App, PluginGroup}, log::LogPlugin};
+
+    use ecsmos_v2::plugins::{
+        default::plugin::ECSMosHeadlessPlugins,
+        scenario_loader::plugin::ScenarioLoaderPlugin,
+        social_foces_model::components::{DesiredSpeed, Mass},
+    };
+
+    // Setup
+
+    let scenario = parse(&["--desired-speed", "1.5", "--mass", "70"]).load_scenario().unwrap();
+
+    let mut app = App::new();
+
+    app.add_plugins(ECSMosHeadlessPlugins.build().disable::<LogPlugin>())
+        .add_plugins(ScenarioLoaderPlugin { scenario });
+
+    // Act
+
+    for _ in 0..10 {
+        app.update();
+    }
+
+    // Assert
+
+    let world = app.world_mut();
+    let parameters: Vec<(f32, f32)> = world.query::<(&DesiredSpeed, &Mass)>().iter(world).map(|(speed, mass)| (speed.0, mass.0)).collect();
+
+    assert!(!parameters.is_empty());
+    assert!(parameters.iter().all(|&parameters| parameters == (1.5, 70.)));
+}
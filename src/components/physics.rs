@@ -1,3 +1,4 @@
+use std::f32::consts::TAU;
 
 use bevy::prelude::*;
 use derive_more::{Add, AddAssign, Constructor, From, Into, Mul, MulAssign, Sub};
@@ -89,40 +90,144 @@ pub fn signed_distance_and_normal_to_sahpe(shape: &Shape, shape_position: Vec2,
 
             let num_points = polygon_points.len();
             let mut min_dist: f32 = f32::INFINITY;
-            let mut segment = (Vec2::ZERO, Vec2::ZERO);
-            let mut t_of_min = 0.;
+            let mut normal = Vec2::ZERO;
 
             for i in 0..num_points{
 
                 let a = polygon_points[i];
                 let b = polygon_points[(i + 1) % num_points];
 
-                let t = (point - a).dot(b - a) / (b - a).dot( b - a);
-
-                let closes_point = a.lerp(b, t.clamp(0., 1.));
-                
-                let distance = (point - closes_point).length();
+                let (segment_normal, distance) = distance_and_normal_to_segment(a, b, point);
 
                 if distance < min_dist{
                     min_dist = distance;
-                    segment = (a, b);
-                    t_of_min = t;
+                    normal = segment_normal;
                 }
             }
 
-            let ab = segment.1 - segment.0;
-            // Vec2::new(ab.y, -ab.x)
-            let normal = match t_of_min {
-                x if x < 0. => point - segment.0,
-                x if x > 1. => point - segment.1,
-                _ => Vec2::new(ab.y, -ab.x)
-            };
-
             return (normal, min_dist);
         }
     }
 }
 
+/// Distance from `point` to the segment `a`-`b`, with the outward normal for a counterclockwise polygon edge
+pub fn distance_and_normal_to_segment(a: Vec2, b: Vec2, point: Vec2) -> (Vec2, f32) {
+    let ab = b - a;
+
+    // Closed polygons repeat their first point, the segment between both copies is a point
+    if ab.length_squared() <= f32::EPSILON {
+        return (point - a, (point - a).length());
+    }
+
+    let t = (point - a).dot(ab) / ab.dot(ab);
+
+    let closes_point = a.lerp(b, t.clamp(0., 1.));
+
+    let normal = match t {
+        x if x < 0. => point - a,
+        x if x > 1. => point - b,
+        _ => Vec2::new(ab.y, -ab.x)
+    };
+
+    (normal, (point - closes_point).length())
+}
+
+/// Area of a simple polygon, positive when its points are counterclockwise
+pub fn polygon_signed_area(points: &[Vec2]) -> f32 {
+    let n = points.len();
+
+    (0..n).map(|i| points[i].perp_dot(points[(i + 1) % n])).sum::<f32>() / 2.
+}
+
+/// Part of the polygon on the side of the line through `point` opposite to `normal` (Sutherland-Hodgman)
+pub fn clip_polygon_to_half_plane(points: &[Vec2], point: Vec2, normal: Vec2) -> Vec<Vec2> {
+    let n = points.len();
+    let mut clipped = Vec::with_capacity(n + 1);
+
+    let side = |p: Vec2| (p - point).dot(normal);
+
+    for i in 0..n {
+        let previous = points[(i + n - 1) % n];
+        let current = points[i];
+
+        let (previous_side, current_side) = (side(previous), side(current));
+
+        if (previous_side <= 0.) != (current_side <= 0.) {
+            let t = previous_side / (previous_side - current_side);
+            clipped.push(previous.lerp(current, t));
+        }
+
+        if current_side <= 0. {
+            clipped.push(current);
+        }
+    }
+
+    clipped
+}
+
+/// Number of vertices the cut off circle of the cells is approximated with
+const CUTOFF_VERTICES: usize = 16;
+
+/// Voronoi cell of `center` among `neighbours`, cut off by a circle of radius `cutoff`, counterclockwise
+///
+/// Only neighbours closer than `2 * cutoff` can shape the cell
+pub fn voronoi_cell(center: Vec2, neighbours: impl Iterator<Item = Vec2>, cutoff: f32) -> Vec<Vec2> {
+    let mut cell: Vec<Vec2> = (0..CUTOFF_VERTICES)
+        .map(|i| center + Vec2::from_angle(TAU * i as f32 / CUTOFF_VERTICES as f32) * cutoff)
+        .collect();
+
+    for neighbour in neighbours {
+        let normal = neighbour - center;
+
+        // Agents on top of each other share the cut off circle
+        if normal.length_squared() < f32::EPSILON {
+            continue;
+        }
+
+        cell = clip_polygon_to_half_plane(&cell, (center + neighbour) / 2., normal);
+    }
+
+    cell
+}
+
+/// Area of the intersection of a polygon, which may be concave, with a convex counterclockwise one
+pub fn intersection_area(polygon: &[Vec2], convex: &[Vec2]) -> f32 {
+    let n = convex.len();
+    let mut clipped = polygon.to_vec();
+
+    for i in 0..n {
+        let (a, b) = (convex[i], convex[(i + 1) % n]);
+        let outward = Vec2::new(b.y - a.y, a.x - b.x);
+
+        clipped = clip_polygon_to_half_plane(&clipped, a, outward);
+
+        if clipped.is_empty() {
+            return 0.;
+        }
+    }
+
+    polygon_signed_area(&clipped).abs()
+}
+
+/// Where the segment `from`-`to` crosses the segment `a`-`b`, as the fraction of `from`-`to` before the crossing,
+/// and the side it crosses to, `1.` when ending on the left of `a`-`b` and `-1.` on its right
+pub fn segment_crossing(a: Vec2, b: Vec2, from: Vec2, to: Vec2) -> Option<(f32, f32)> {
+    let ab = b - a;
+    let movement = to - from;
+
+    let (side_from, side_to) = (ab.perp_dot(from - a), ab.perp_dot(to - a));
+
+    // Starting on the line counts as being on its right, so touching it and going back is not a crossing
+    if (side_from > 0.) == (side_to > 0.) {
+        return None;
+    }
+
+    let t = movement.perp_dot(a - from) / ab.perp_dot(movement);
+    let fraction = side_from / (side_from - side_to);
+
+    (0. ..=1.).contains(&t).then_some((fraction, if side_to > 0. { 1. } else { -1. }))
+}
+
 pub trait Coordinate : Sized + Copy{
     fn adjacent(&self) -> Vec<Self>;
 
@@ -145,4 +250,57 @@ impl Coordinate for IVec2 {
         .map(|&dir| self + dir)
         .collect()
     }
-}
\ No newline at end of file
+}
+
+// #######
+// Testing
+// #######
+
+#[test]
+fn check_cells_of_a_grid_are_squares() {
+
+    // Setup
+
+    let neighbours = [Vec2::X, -Vec2::X, Vec2::Y, -Vec2::Y, Vec2::ONE, -Vec2::ONE, Vec2::new(1., -1.), Vec2::new(-1., 1.)];
+
+    // Act
+
+    let cell = voronoi_cell(Vec2::ZERO, neighbours.into_iter(), 1.);
+
+    // Assert
+
+    assert!((polygon_signed_area(&cell) - 1.).abs() < 1e-4);
+}
+
+#[test]
+fn check_isolated_cell_is_the_cutoff_circle() {
+
+    // Act
+
+    let cell = voronoi_cell(Vec2::new(3., 4.), std::iter::empty(), 1.);
+
+    // Assert
+
+    let area = polygon_signed_area(&cell);
+    assert!(area > 3. && area < std::f32::consts::PI);
+}
+
+#[test]
+fn check_intersection_with_concave_polygon() {
+
+    // Setup
+
+    // L shaped polygon covering [0,2]x[0,2] without [1,2]x[1,2]
+    let polygon = [
+        Vec2::new(0., 0.), Vec2::new(2., 0.), Vec2::new(2., 1.), Vec2::new(1., 1.), Vec2::new(1., 2.), Vec2::new(0., 2.),
+    ];
+    let square = [Vec2::new(0.5, 0.5), Vec2::new(2.5, 0.5), Vec2::new(2.5, 2.5), Vec2::new(0.5, 2.5)];
+
+    // Act
+
+    let area = intersection_area(&polygon, &square);
+
+    // Assert
+
+    assert!((area - 1.25).abs() < 1e-5);
+}
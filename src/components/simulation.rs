@@ -10,4 +10,31 @@ pub struct Objective;
 pub struct Obstacle;
 
 #[derive(Component, Copy, Clone)]
-pub struct Destination(pub Entity);
\ No newline at end of file
+pub struct Destination(pub Entity);
+
+/// Ordered objectives an agent visits, its `Destination` is always the current one
+#[derive(Component, Clone)]
+pub struct Itinerary {
+    waypoints: Vec<Entity>,
+    current: usize,
+}
+
+impl Itinerary {
+    pub fn new(waypoints: Vec<Entity>) -> Self {
+        Self { waypoints, current: 0 }
+    }
+
+    pub fn current(&self) -> Option<Entity> {
+        self.waypoints.get(self.current).copied()
+    }
+
+    /// Moves to the next waypoint, returns `None` once the final one has been reached
+    pub fn advance(&mut self) -> Option<Entity> {
+        self.current = (self.current + 1).min(self.waypoints.len());
+        self.current()
+    }
+
+    pub fn waypoints(&self) -> &[Entity] {
+        &self.waypoints
+    }
+}
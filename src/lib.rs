@@ -0,0 +1,5 @@
+pub mod components;
+pub mod plugins;
+pub mod resources;
+pub mod scenarios;
+pub mod utils;
@@ -1,13 +1,73 @@
 pub mod plugin {
-    use bevy::app::{Last, Plugin};
+    use bevy::{app::Plugin, ecs::schedule::IntoScheduleConfigs};
 
-    use super::systems::exit_when_no_agents;
+    use crate::plugins::{simple_objective::systems::record_arrivals, simulation_clock::plugin::SimulationPostUpdate};
 
-    pub struct AutoEndSimulationPlugin;
+    use super::{resources::*, systems::*};
+
+    /// Ends the simulation as soon as any of the `conditions` is met
+    pub struct AutoEndSimulationPlugin {
+        pub conditions: Vec<StopCondition>,
+    }
+
+    impl Default for AutoEndSimulationPlugin {
+        fn default() -> Self {
+            Self {
+                conditions: vec![StopCondition::SpawnersFinishedAndNoAgents],
+            }
+        }
+    }
 
     impl Plugin for AutoEndSimulationPlugin {
         fn build(&self, app: &mut bevy::app::App) {
-            app.add_systems(Last, exit_when_no_agents);
+            app.insert_resource(StopConditions(self.conditions.clone()))
+                .init_resource::<StopConditionsState>()
+                .add_systems(SimulationPostUpdate, exit_when_stop_condition_met.after(record_arrivals));
+        }
+    }
+}
+
+pub mod resources {
+    use std::time::Instant;
+
+    use bevy::ecs::resource::Resource;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub enum StopCondition {
+        /// Simulated time after which the simulation ends (s)
+        MaxSimulatedTime(f32),
+
+        /// Real time after which the simulation ends (s)
+        MaxWallClockTime(f32),
+
+        /// Every spawner is past its end time and no agents are left
+        SpawnersFinishedAndNoAgents,
+
+        /// Number of agents that reached their final objective
+        Arrivals(u32),
+
+        /// Mean agent speed stayed below `speed` (m/s) for `duration` (s)
+        Gridlock { speed: f32, duration: f32 },
+    }
+
+    #[derive(Resource, Clone)]
+    pub struct StopConditions(pub Vec<StopCondition>);
+
+    #[derive(Resource)]
+    pub struct StopConditionsState {
+        pub wall_clock_start: Instant,
+
+        /// Simulated time the agents started to be slower than the gridlock speed at (s), indexed like the `StopConditions`
+        pub slow_since: Vec<Option<f32>>,
+    }
+
+    impl Default for StopConditionsState {
+        fn default() -> Self {
+            Self {
+                wall_clock_start: Instant::now(),
+                slow_since: Vec::new(),
+            }
         }
     }
 }
@@ -15,14 +75,154 @@ pub mod plugin {
 pub mod systems {
     use bevy::prelude::*;
 
-    use crate::components::prelude::Agent;
+    use crate::{
+        components::prelude::{Agent, Speed},
+        plugins::{simple_objective::resources::ArrivalStatistics, spawner::components::SpawnerSchedule},
+        resources::configuration::{SimulationConfiguration, SimulationTime},
+    };
+
+    use super::resources::*;
 
-    pub fn exit_when_no_agents(
+    #[allow(clippy::too_many_arguments)]
+    pub fn exit_when_stop_condition_met(
+        config: Res<SimulationConfiguration>,
+        time: Res<SimulationTime>,
+        conditions: Res<StopConditions>,
+        mut state: ResMut<StopConditionsState>,
+        statistics: Option<Res<ArrivalStatistics>>,
         mut app_exit_events: EventWriter<AppExit>,
-        agents: Query<Entity, With<Agent>>,
+        agents: Query<&Speed, With<Agent>>,
+        spawners: Query<&SpawnerSchedule>,
     ) {
-        if agents.iter().len() == 0 {
-            app_exit_events.write(AppExit::Success);
+        // The clock is only advanced after the tick, so the current tick ends one time step later
+        let end_of_tick = time.elapsed() + config.simulation_time_step;
+
+        let agent_count = agents.iter().len();
+
+        let mean_speed = match agent_count {
+            0 => None,
+            n => Some(agents.iter().map(|s| s.value().length()).sum::<f32>() / n as f32),
+        };
+
+        state.slow_since.resize(conditions.0.len(), None);
+
+        for (i, condition) in conditions.0.iter().enumerate() {
+            let met = match *condition {
+                StopCondition::MaxSimulatedTime(max) => end_of_tick >= max,
+                StopCondition::MaxWallClockTime(max) => state.wall_clock_start.elapsed().as_secs_f32() >= max,
+                StopCondition::SpawnersFinishedAndNoAgents => {
+                    agent_count == 0 && spawners.iter().all(|schedule| time.elapsed() > schedule.end_time)
+                }
+                StopCondition::Arrivals(target) => statistics
+                    .as_ref()
+                    .is_some_and(|statistics| statistics.exits().count() >= target as usize),
+                StopCondition::Gridlock { speed, duration } => match mean_speed {
+                    Some(mean_speed) if mean_speed < speed => {
+                        let since = *state.slow_since[i].get_or_insert(time.elapsed());
+                        end_of_tick - since >= duration
+                    }
+                    _ => {
+                        state.slow_since[i] = None;
+                        false
+                    }
+                },
+            };
+
+            if met {
+                info!("Stop condition {:?} met, ending simulation at {:.2}s", condition, end_of_tick);
+                app_exit_events.write(AppExit::Success);
+                return;
+            }
         }
     }
+
+    // #######
+    // Testing
+    // #######
+
+    #[cfg(test)]
+    fn run_until_exit(conditions: Vec<StopCondition>, setup: impl FnOnce(&mut World), max_ticks: u32) -> Option<u32> {
+        let mut app = App::new();
+
+        app.add_event::<AppExit>()
+            .insert_resource(SimulationConfiguration { simulation_time_step: 1. })
+            .init_resource::<SimulationTime>()
+            .insert_resource(StopConditions(conditions))
+            .init_resource::<StopConditionsState>()
+            .add_systems(Update, exit_when_stop_condition_met);
+
+        setup(app.world_mut());
+
+        for tick in 0..max_ticks {
+            app.update();
+
+            if app.world().resource::<Events<AppExit>>().iter_current_update_events().count() > 0 {
+                return Some(tick);
+            }
+
+            app.world_mut().resource_mut::<SimulationTime>().advance(1.);
+        }
+
+        None
+    }
+
+    #[test]
+    fn check_waits_for_spawners_before_ending() {
+        let tick = run_until_exit(
+            vec![StopCondition::SpawnersFinishedAndNoAgents],
+            |world| {
+                world.spawn(SpawnerSchedule { interval: 1., last_spawn: 0., start_time: 3., end_time: 5. });
+            },
+            20,
+        );
+
+        assert_eq!(tick, Some(6));
+    }
+
+    #[test]
+    fn check_max_simulated_time() {
+        let tick = run_until_exit(vec![StopCondition::MaxSimulatedTime(10.)], |_| {}, 20);
+
+        assert_eq!(tick, Some(9));
+    }
+
+    #[test]
+    fn check_gridlock_is_detected() {
+        let tick = run_until_exit(
+            vec![StopCondition::Gridlock { speed: 0.1, duration: 4. }],
+            |world| {
+                world.spawn((Agent, Speed::new(Vec2::new(0.05, 0.))));
+                world.spawn((Agent, Speed::new(Vec2::new(0., 0.))));
+            },
+            20,
+        );
+
+        assert_eq!(tick, Some(3));
+    }
+
+    #[test]
+    fn check_gridlock_conditions_are_timed_separately() {
+        let tick = run_until_exit(
+            vec![StopCondition::Gridlock { speed: 0.5, duration: 4. }, StopCondition::Gridlock { speed: 0.1, duration: 4. }],
+            |world| {
+                world.spawn((Agent, Speed::new(Vec2::new(0.3, 0.))));
+            },
+            20,
+        );
+
+        assert_eq!(tick, Some(3));
+    }
+
+    #[test]
+    fn check_moving_agents_are_not_gridlocked() {
+        let tick = run_until_exit(
+            vec![StopCondition::Gridlock { speed: 0.1, duration: 4. }],
+            |world| {
+                world.spawn((Agent, Speed::new(Vec2::new(1., 0.))));
+            },
+            20,
+        );
+
+        assert_eq!(tick, None);
+    }
 }
@@ -1,12 +1,15 @@
 use bevy::diagnostic::LogDiagnosticsPlugin;
+use bevy::log::LogPlugin;
 use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
 use bevy::window::PresentMode;
 use bevy::{app::{PluginGroup, PluginGroupBuilder}, window::{Window, WindowPlugin}, DefaultPlugins};
 use bevy_fps_counter::FpsCounterPlugin;
 use bevy_pancam::PanCamPlugin;
 use bevy_prototype_lyon::prelude::*;
 
-use crate::plugins::display::plugin::DisplayPlugin;
+use crate::plugins::{display::plugin::DisplayPlugin, simulation_clock::plugin::SimulationClockPlugin};
+use crate::resources::configuration::SimulationMode;
 
 pub struct ECSMosDefaultPlugins;
 
@@ -33,5 +36,25 @@ impl PluginGroup for ECSMosDefaultPlugins {
 
         // Internal
         .add_after::<PanCamPlugin>(DisplayPlugin)
+        .add(SimulationClockPlugin { mode: SimulationMode::RealTime })
     }
-}
\ No newline at end of file
+}
+
+/// Plugins required to run a simulation without a window or renderer.
+///
+/// Intended for CI and batch runs, the schedule is driven by the `ScheduleRunnerPlugin` as fast as possible.
+pub struct ECSMosHeadlessPlugins;
+
+impl PluginGroup for ECSMosHeadlessPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+
+        // Bevy Base
+        .add_group(MinimalPlugins)
+        .add(LogPlugin::default())
+        .add(StatesPlugin)
+
+        // Internal
+        .add(SimulationClockPlugin { mode: SimulationMode::AsFastAsPossible })
+    }
+}
@@ -1,7 +1,7 @@
-use bevy::{color::palettes::tailwind::GRAY_500, prelude::*};
+use bevy::{color::palettes::tailwind::{BLUE_500, GRAY_400, GRAY_500, GREEN_500}, prelude::*};
 use bevy_prototype_lyon::{path::ShapePath, prelude::{ShapeBuilder, ShapeBuilderBase}};
 
-use crate::components::prelude::*;
+use crate::{components::prelude::*, plugins::measurement::components::MeasurementArea};
 
 use super::resources::DisplayConfiguration;
 
@@ -23,14 +23,25 @@ pub fn add_transform_for_positioned_components(
 pub fn add_mesh_for_shaped_components(
     config: Res<DisplayConfiguration>,
     mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
     mut commands: Commands, 
-    query: Query<(Entity, &Shape), (Without<Mesh2d>, Without<bevy_prototype_lyon::entity::Shape>)>
+    query: Query<(Entity, &Shape, Has<MeshMaterial2d<ColorMaterial>>, Has<Agent>, Has<Objective>), (Without<Mesh2d>, Without<bevy_prototype_lyon::entity::Shape>, Without<MeasurementArea>)>
 ){
-    for (entity, shape) in query.iter() {
+    for (entity, shape, has_material, is_agent, is_objective) in query.iter() {
 
         if let Shape::Circle(radius) = shape {
             let mesh = meshes.add(Circle { radius:radius * config.pixels_per_meter});
             commands.entity(entity).insert(Mesh2d(mesh));
+
+            if !has_material {
+                let color = match (is_agent, is_objective) {
+                    (true, _) => BLUE_500,
+                    (_, true) => GREEN_500,
+                    _ => GRAY_400,
+                };
+
+                commands.entity(entity).insert(MeshMaterial2d(materials.add(Color::from(color))));
+            }
         }
         
         else if let Shape::Polygon(points) = shape {
@@ -1,4 +1,5 @@
 use bevy::ecs::resource::Resource;
+use serde::{Deserialize, Serialize};
 
 #[derive(Resource)]
 pub struct GridCellSize{
@@ -6,14 +7,41 @@ pub struct GridCellSize{
     pub columns: usize,
 }
 
-#[derive(Resource, Clone, Copy)]
+#[derive(Resource, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct FlowFieldConstants{
     pub influence_radius_multiplier: f32,
     pub kernel_radius_overflow: f32,
+
+    /// Number of ticks between two computations of the fields, only the cells whose inputs changed are computed again
+    pub recompute_interval: u32,
 }
 
 impl Default for FlowFieldConstants {
     fn default() -> Self {
-        Self { influence_radius_multiplier: 10., kernel_radius_overflow:10. }
+        Self { influence_radius_multiplier: 10., kernel_radius_overflow:10., recompute_interval: 1 }
     }
-}
\ No newline at end of file
+}
+
+/// How the agents are spread over the `AgentDensity` fields
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum DensityComputationStrategy {
+    /// Each agent is spread over the cells around it by a kernel sized by `influence_radius_multiplier` and `kernel_radius_overflow`
+    #[default]
+    Kernel,
+
+    /// Each cell gets the share of the Voronoi cells of the agents covering it (1/m²),
+    /// the Voronoi cells are cut off at the `VoronoiCutoff` and clipped by the obstacles and the simulation area
+    Voronoi,
+}
+
+/// How the distances of the `TargetProximity` fields are computed
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ProximityComputationStrategy {
+    /// Shortest paths through the 8 neighbours of each cell, straight and diagonal steps make them up to 8% longer than a straight line
+    #[default]
+    Dijkstra,
+
+    /// First order fast marching solution of the Eikonal equation, closer to straight line distances away from the targets
+    FastMarching,
+}
@@ -3,4 +3,5 @@ pub mod models;
 pub mod resources;
 pub mod systems;
 pub mod components;
-pub mod configuration;
\ No newline at end of file
+pub mod configuration;
+pub mod solvers;
\ No newline at end of file
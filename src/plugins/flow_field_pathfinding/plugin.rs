@@ -1,27 +1,38 @@
 use bevy::prelude::*;
 
-use crate::{plugins::simulation_area::resources::SimulationArea, Obstacle};
+use crate::{components::prelude::Obstacle, plugins::{display::resources::DisplayConfiguration, simulation_area::resources::SimulationArea, simulation_clock::plugin::SimulationUpdate, social_foces_model::plugin::add_obstacle_edge_grid}, resources::configuration::VoronoiCutoff};
 
-use super::{configuration::{FlowFieldConstants, GridCellSize}, models::{AgentDensity, BlockedStatus, TargetProximity, TargetStatus}, resources::*, systems::*};
+use super::{configuration::{DensityComputationStrategy, FlowFieldConstants, GridCellSize, ProximityComputationStrategy}, models::{AgentDensity, BlockedStatus, TargetProximity, TargetStatus}, resources::*, systems::*};
 
 #[derive(Default)]
 pub struct FlowFieldPathfindingPlugin{
     pub cell_size: f32,
 
     pub constants: FlowFieldConstants,
+
+    pub density: DensityComputationStrategy,
+
+    pub proximity: ProximityComputationStrategy,
 }
 
 impl Plugin for FlowFieldPathfindingPlugin {
     fn build(&self, app: &mut App) {
 
         let cell_size = self.cell_size;
+        let density = self.density;
 
         app
         .insert_state(PathFindingOverlayState::ShowNone)
         .insert_state(ShowGridState::HideGrid)
 
         .insert_resource(SelectedItem(0))
-        .insert_resource(self.constants);
+        .insert_resource(self.constants)
+        .insert_resource(self.proximity)
+
+        .init_resource::<DirtyRegion<BlockedStatus>>()
+        .init_resource::<DirtyRegion<AgentDensity>>()
+        .init_resource::<EntityDirtyRegions<TargetStatus>>()
+        .init_resource::<EntityDirtyRegions<TargetProximity>>();
 
         register_multi_field::<TargetStatus>(app);
         register_multi_field::<TargetProximity>(app);
@@ -34,27 +45,44 @@ impl Plugin for FlowFieldPathfindingPlugin {
 
         .add_systems(Startup, add_field_map::<BlockedStatus>)
 
-        .add_systems(PreUpdate, handle_grid_state_inputs)
-        .add_systems(PreUpdate, handle_overlay_inputs)
-        .add_systems(PreUpdate, handle_selection_inputs)
+        .add_systems(PreUpdate, 
+            (handle_grid_state_inputs, handle_overlay_inputs, handle_selection_inputs)
+            .run_if(resource_exists::<ButtonInput<KeyCode>>)
+        )
         
-        .add_systems(Update, 
+        .configure_sets(SimulationUpdate, FlowFieldSystemSet::ComputeFields.run_if(recompute_fields_this_tick))
+
+        .add_systems(SimulationUpdate, 
             (
-                (compute_colision_map::<BlockedStatus, Obstacle>, compute_objective_colision_map, compute_density_map),
+                (
+                    compute_colision_map::<BlockedStatus, Obstacle>, 
+                    compute_objective_colision_map, 
+                    compute_density_map.run_if(move || density == DensityComputationStrategy::Kernel),
+                    compute_voronoi_density_map.run_if(move || density == DensityComputationStrategy::Voronoi),
+                ),
+                mark_density_changes,
                 compute_proximity_map,
                 compute_vector_map
             ).chain().in_set(FlowFieldSystemSet::ComputeFields)
         )
         
-        .add_systems(PostUpdate, draw_grid.run_if(in_state(ShowGridState::ShowGrid)))
-
-        .add_systems(PostUpdate, draw_obstacles.run_if(in_state(PathFindingOverlayState::ShowObstacles)))
-        .add_systems(PostUpdate, draw_targets.run_if(in_state(PathFindingOverlayState::ShowTargets)))
-        .add_systems(PostUpdate, draw_proximity.run_if(in_state(PathFindingOverlayState::ShowProimity)))
-        .add_systems(PostUpdate, draw_vectors.run_if(in_state(PathFindingOverlayState::ShowVectorField)))
-        .add_systems(PostUpdate, draw_density.run_if(in_state(PathFindingOverlayState::ShowDensityField)))
+        .add_systems(PostUpdate, 
+            (
+                draw_grid.run_if(in_state(ShowGridState::ShowGrid)),
+                draw_obstacles.run_if(in_state(PathFindingOverlayState::ShowObstacles)),
+                draw_targets.run_if(in_state(PathFindingOverlayState::ShowTargets)),
+                draw_proximity.run_if(in_state(PathFindingOverlayState::ShowProimity)),
+                draw_vectors.run_if(in_state(PathFindingOverlayState::ShowVectorField)),
+                draw_density.run_if(in_state(PathFindingOverlayState::ShowDensityField)),
+            ).run_if(resource_exists::<DisplayConfiguration>)
+        )
         
         .add_systems(Last, remove_field_for_objectives);
+
+        if density == DensityComputationStrategy::Voronoi {
+            let cutoff = app.world_mut().get_resource_or_init::<VoronoiCutoff>().0;
+            add_obstacle_edge_grid(app, cutoff);
+        }
     }
 }
 
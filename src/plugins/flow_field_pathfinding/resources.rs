@@ -1,5 +1,5 @@
-use bevy::prelude::*;
-use std::{collections::HashMap, fmt::{self, Debug}, hash::Hash};
+use bevy::{platform::collections::{hash_map, HashMap}, prelude::*};
+use std::{fmt::{self, Debug}, hash::Hash, marker::PhantomData};
 
 
 
@@ -176,6 +176,46 @@ where T : Clone
         Some(IRect::from_center_size(search_center, size + IVec2::ONE))
     }
 
+    /// Cells touching `search_area`, clamped to the grid, `max` is exclusive
+    pub fn get_cells_overlapping(&self, search_area: Rect) -> Option<IRect>{
+        let grid_size = IVec2::new(self.get_columns() as i32, self.get_rows() as i32);
+
+        let min = self.get_cell_at_unbound(search_area.min).max(IVec2::ZERO);
+        let max = (self.get_cell_at_unbound(search_area.max) + IVec2::ONE).min(grid_size);
+
+        if min.x >= max.x || min.y >= max.y {
+            return None;
+        }
+
+        Some(IRect::from_corners(min, max))
+    }
+
+    /// Every cell of the grid, `max` is exclusive
+    pub fn get_bounds(&self) -> IRect{
+        IRect::new(0, 0, self.get_columns() as i32, self.get_rows() as i32)
+    }
+
+    /// Cells whose value differs from `previous`, which has to come from a field of the same size
+    pub fn get_changed_cells(&self, previous: &[T]) -> Option<IRect> where T: PartialEq{
+        let mut region = DirtyRegion::<T>::default();
+
+        for (index, (value, previous)) in self.as_vec().iter().zip(previous).enumerate() {
+            if value != previous {
+                let cell = IVec2::new((index % self.get_columns()) as i32, (index / self.get_columns()) as i32);
+                region.mark(IRect::from_corners(cell, cell + IVec2::ONE));
+            }
+        }
+
+        region.take()
+    }
+
+    /// Corners of the cell, counterclockwise
+    pub fn get_cell_outline(&self, cell: IVec2) -> [Vec2; 4]{
+        let rect = Rect::from_center_size(self.get_coord(cell), self.cell_dimentions);
+
+        [rect.min, Vec2::new(rect.max.x, rect.min.y), rect.max, Vec2::new(rect.min.x, rect.max.y)]
+    }
+
     pub fn get_coord(&self, cell: IVec2) -> Vec2{
         let mim_coord = (cell.as_vec2() - Vec2::new(self.get_columns() as f32, self.get_rows() as f32) / 2.) * self.cell_dimentions * 2.;
         
@@ -287,11 +327,11 @@ impl<K, V> MultiField<K, V> where V : Clone , K : Eq + Hash{
         }
     }
 
-    pub fn iter(&self) -> std::collections::hash_map::Iter<'_, K, Field<V>>{
+    pub fn iter(&self) -> hash_map::Iter<'_, K, Field<V>>{
         return self.map.iter();
     }
 
-    pub fn iter_mut(&mut self) -> std::collections::hash_map::IterMut<'_, K, Field<V>>{
+    pub fn iter_mut(&mut self) -> hash_map::IterMut<'_, K, Field<V>>{
         return self.map.iter_mut();
     }
 
@@ -300,4 +340,66 @@ impl<K, V> MultiField<K, V> where V : Clone , K : Eq + Hash{
     }
 
     
-}
\ No newline at end of file
+}
+
+/// Cells of the `Field<T>` or `EntityMultiField<T>` that changed since the fields depending on them were last computed
+#[derive(Resource)]
+pub struct DirtyRegion<T>{
+    region: Option<IRect>,
+    marker: PhantomData<T>,
+}
+
+impl<T> Default for DirtyRegion<T> {
+    fn default() -> Self {
+        Self { region: None, marker: PhantomData }
+    }
+}
+
+impl<T> DirtyRegion<T> {
+    /// `max` of the cells is exclusive
+    pub fn mark(&mut self, cells: IRect){
+        self.region = Some(self.region.map_or(cells, |region| region.union(cells)));
+    }
+
+    pub fn get(&self) -> Option<IRect>{
+        self.region
+    }
+
+    pub fn take(&mut self) -> Option<IRect>{
+        self.region.take()
+    }
+}
+
+/// `DirtyRegion` of each field of an `EntityMultiField<T>`
+#[derive(Resource)]
+pub struct EntityDirtyRegions<T>{
+    regions: HashMap<Entity, DirtyRegion<T>>,
+}
+
+impl<T> Default for EntityDirtyRegions<T> {
+    fn default() -> Self {
+        Self { regions: HashMap::new() }
+    }
+}
+
+impl<T> EntityDirtyRegions<T> {
+    pub fn mark(&mut self, entity: Entity, cells: IRect){
+        self.regions.entry(entity).or_default().mark(cells);
+    }
+
+    pub fn get(&self, entity: &Entity) -> Option<IRect>{
+        self.regions.get(entity).and_then(DirtyRegion::get)
+    }
+
+    pub fn take(&mut self, entity: &Entity) -> Option<IRect>{
+        self.regions.remove(entity).and_then(|mut region| region.take())
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.regions.values().all(|region| region.get().is_none())
+    }
+
+    pub fn clear(&mut self){
+        self.regions.clear();
+    }
+}
@@ -0,0 +1,231 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use bevy::math::IVec2;
+
+use crate::components::prelude::Coordinate;
+
+use super::{configuration::ProximityComputationStrategy, models::TargetProximity, resources::*};
+
+/// Axis aligned neighbours, the stencil of the fast marching method
+const AXIS_NEIGHBOURS: [IVec2; 4] = [IVec2::NEG_X, IVec2::X, IVec2::NEG_Y, IVec2::Y];
+
+/// Cell waiting to be settled, the heap pops the closest one first
+#[derive(Clone, Copy, PartialEq)]
+struct OpenCell {
+    distance: f32,
+    cell: IVec2,
+}
+
+impl Eq for OpenCell {}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance)
+            .then_with(|| other.cell.x.cmp(&self.cell.x))
+            .then_with(|| other.cell.y.cmp(&self.cell.y))
+    }
+}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Fills the `NotComputed` cells with their distance (cells) to the `Computed` ones, spreading from `seeds`
+///
+/// `penalty` is the extra cost of entering a cell, the cells next to obstacles become `Buffer` and are not crossed
+pub fn solve_proximity(
+    strategy: ProximityComputationStrategy,
+    proximity_map: &mut Field<TargetProximity>,
+    seeds: impl IntoIterator<Item = IVec2>,
+    penalty: impl Fn(IVec2) -> f32){
+
+    let columns = proximity_map.get_columns();
+    let index = |cell: IVec2| cell.x as usize + cell.y as usize * columns;
+
+    // Distances of known cells are final, the ones of the other cells can still go down
+    let mut known: Vec<bool> = proximity_map.as_vec().iter().map(|value| matches!(value, TargetProximity::Computed(_))).collect();
+    let mut expanded = vec![false; known.len()];
+
+    let mut open_list: BinaryHeap<OpenCell> = seeds.into_iter()
+        .filter_map(|cell| match proximity_map.get(&cell) {
+            Some(&TargetProximity::Computed(distance)) => Some(OpenCell { distance, cell }),
+            _ => None,
+        })
+        .collect();
+
+    while let Some(OpenCell { distance, cell }) = open_list.pop() {
+
+        if expanded[index(cell)] {
+            continue;
+        }
+
+        expanded[index(cell)] = true;
+        known[index(cell)] = true;
+
+        let any_invalid_coordinate = cell.adjacent()
+            .iter()
+            .any(|coord| proximity_map.get(coord) == Some(&TargetProximity::Obstacle));
+
+        if any_invalid_coordinate {
+            proximity_map.set(cell, TargetProximity::Buffer).unwrap();
+            continue;
+        }
+
+        let neighbours = match strategy {
+            ProximityComputationStrategy::Dijkstra => cell.adjacent(),
+            ProximityComputationStrategy::FastMarching => AXIS_NEIGHBOURS.iter().map(|direction| cell + direction).collect(),
+        };
+
+        for neighbour in neighbours {
+
+            let current = match proximity_map.get(&neighbour) {
+                Some(_) if known[index(neighbour)] => continue,
+                Some(TargetProximity::NotComputed) => f32::INFINITY,
+                Some(TargetProximity::Computed(value)) => *value,
+                _ => continue,
+            };
+
+            let new_distance = match strategy {
+                ProximityComputationStrategy::Dijkstra => distance + (neighbour - cell).as_vec2().length() + penalty(neighbour),
+                ProximityComputationStrategy::FastMarching => eikonal_update(proximity_map, &known, &index, neighbour, 1. + penalty(neighbour)),
+            };
+
+            if new_distance < current {
+                proximity_map.set(neighbour, TargetProximity::Computed(new_distance)).unwrap();
+                open_list.push(OpenCell { distance: new_distance, cell: neighbour });
+            }
+        }
+    }
+}
+
+/// First order upwind solution of `|∇T| = slowness` at `cell` from its known axis aligned neighbours
+fn eikonal_update(
+    proximity_map: &Field<TargetProximity>,
+    known: &[bool],
+    index: &impl Fn(IVec2) -> usize,
+    cell: IVec2,
+    slowness: f32) -> f32{
+
+    let known_distance = |direction: IVec2| {
+        let neighbour = cell + direction;
+
+        match proximity_map.get(&neighbour) {
+            Some(&TargetProximity::Computed(value)) if known[index(neighbour)] => value,
+            _ => f32::INFINITY,
+        }
+    };
+
+    let a = known_distance(IVec2::NEG_X).min(known_distance(IVec2::X));
+    let b = known_distance(IVec2::NEG_Y).min(known_distance(IVec2::Y));
+
+    // Only one direction is known or the wave comes along an axis
+    if (a - b).abs() >= slowness {
+        return a.min(b) + slowness;
+    }
+
+    (a + b + (2. * slowness * slowness - (a - b).powi(2)).sqrt()) / 2.
+}
+
+// #######
+// Testing
+// #######
+
+#[cfg(test)]
+fn distances_to_center(strategy: ProximityComputationStrategy) -> (Field<TargetProximity>, IVec2) {
+    use bevy::math::{Rect, Vec2};
+
+    let mut proximity_map = Field::new(41, 41, Rect::from_center_size(Vec2::ZERO, Vec2::splat(41.)), TargetProximity::NotComputed);
+    let center = IVec2::new(20, 20);
+
+    proximity_map.set(center, TargetProximity::Computed(0.)).unwrap();
+
+    solve_proximity(strategy, &mut proximity_map, [center], |_| 0.);
+
+    (proximity_map, center)
+}
+
+/// Largest relative difference with the straight line distance, among the cells at least `min_distance` away from the center
+#[cfg(test)]
+fn max_relative_error(proximity_map: &Field<TargetProximity>, center: IVec2, min_distance: f32) -> f32 {
+    let mut max_error: f32 = 0.;
+
+    for x in 0..proximity_map.get_columns() as i32 {
+        for y in 0..proximity_map.get_rows() as i32 {
+            let cell = IVec2::new(x, y);
+
+            let euclidean = (cell - center).as_vec2().length();
+
+            if cell == center || euclidean < min_distance {
+                continue;
+            }
+
+            let Some(TargetProximity::Computed(distance)) = proximity_map.get(&cell) else {
+                panic!("Cell {} not computed", cell);
+            };
+
+            max_error = max_error.max((distance - euclidean).abs() / euclidean);
+        }
+    }
+
+    max_error
+}
+
+#[test]
+fn check_dijkstra_distances_on_an_empty_grid_are_euclidean() {
+
+    // Act
+
+    let (proximity_map, center) = distances_to_center(ProximityComputationStrategy::Dijkstra);
+
+    // Assert
+
+    // Paths are made of straight and diagonal steps, which are at most 8% longer than the straight line
+    assert!(max_relative_error(&proximity_map, center, 0.) < 0.09);
+    assert_eq!(proximity_map.get(&IVec2::new(30, 20)), Some(&TargetProximity::Computed(10.)));
+    assert!(matches!(proximity_map.get(&IVec2::new(30, 30)), Some(TargetProximity::Computed(value)) if (value - 200f32.sqrt()).abs() < 1e-4));
+}
+
+#[test]
+fn check_fast_marching_distances_on_an_empty_grid_are_euclidean() {
+
+    // Act
+
+    let (proximity_map, center) = distances_to_center(ProximityComputationStrategy::FastMarching);
+
+    // Assert
+
+    // The first order scheme is off by up to 21% next to a point target, the error goes down with the distance
+    assert!(max_relative_error(&proximity_map, center, 10.) < 0.07);
+    assert!(max_relative_error(&proximity_map, center, 20.) < 0.05);
+    assert_eq!(proximity_map.get(&IVec2::new(30, 20)), Some(&TargetProximity::Computed(10.)));
+}
+
+#[test]
+fn check_dijkstra_goes_around_obstacles() {
+
+    // Setup
+
+    use bevy::math::{Rect, Vec2};
+
+    let mut proximity_map = Field::new(7, 7, Rect::from_center_size(Vec2::ZERO, Vec2::splat(7.)), TargetProximity::NotComputed);
+
+    // Wall on column 3 with an opening on the two top rows
+    for y in 0..5 {
+        proximity_map.set(IVec2::new(3, y), TargetProximity::Obstacle).unwrap();
+    }
+
+    proximity_map.set(IVec2::new(0, 0), TargetProximity::Computed(0.)).unwrap();
+
+    // Act
+
+    solve_proximity(ProximityComputationStrategy::Dijkstra, &mut proximity_map, [IVec2::new(0, 0)], |_| 0.);
+
+    // Assert
+
+    // Cells next to the wall are buffers, paths stay away from it and cross at the opening
+    assert_eq!(proximity_map.get(&IVec2::new(2, 0)), Some(&TargetProximity::Buffer));
+    assert_eq!(proximity_map.get(&IVec2::new(0, 4)), Some(&TargetProximity::Computed(4.)));
+    assert!(matches!(proximity_map.get(&IVec2::new(6, 0)), Some(TargetProximity::Computed(value)) if *value > 10.));
+}
@@ -1,10 +1,10 @@
-use std::{collections::VecDeque, f32::consts::PI};
+use std::f32::consts::PI;
 
-use bevy::{color::palettes::tailwind::*, prelude::*};
+use bevy::{color::palettes::tailwind::*, platform::collections::HashMap, prelude::*, tasks::ComputeTaskPool};
 
-use crate::{components::prelude::*, plugins::{display::resources::DisplayConfiguration, simulation_area::resources::SimulationArea}};
+use crate::{components::prelude::*, resources::configuration::{SimulationTime, VoronoiCutoff}, plugins::{display::resources::DisplayConfiguration, simulation_area::resources::SimulationArea, social_foces_model::resources::{AgentSpatialHash, AgentSpatialHashEntry, ObstacleEdgeGrid}}};
 
-use super::{components::Ordering, configuration::{FlowFieldConstants, GridCellSize}, models::*, resources::*};
+use super::{components::Ordering, configuration::{FlowFieldConstants, GridCellSize, ProximityComputationStrategy}, models::*, resources::*, solvers::solve_proximity};
 
 // #############
 // Setup Systems
@@ -54,6 +54,7 @@ pub fn remove_field_for_objectives(
 
 pub fn compute_objective_colision_map(
     mut grid_multi_map: ResMut<EntityMultiField<TargetStatus>>, 
+    mut dirty_targets: ResMut<EntityDirtyRegions<TargetStatus>>,
     objectives: Query<(Entity, &Position, &Shape), (With<Objective>, Changed<Position>)>
 ){
     for (e, position, shape) in objectives.into_iter() {
@@ -61,6 +62,9 @@ pub fn compute_objective_colision_map(
 
         map.reset(TargetStatus::default());
 
+        // Every distance to the objective depends on where it is
+        dirty_targets.mark(e, map.get_bounds());
+
         let center = position.value();
         let rect = shape.get_rectangle_with_center(center);
 
@@ -102,193 +106,275 @@ pub fn compute_objective_colision_map(
 
 pub fn compute_colision_map<T, U>(
     mut map: ResMut<Field<T>>, 
-    targets: Query<(&Position, &Shape), (With<U>, Changed<Position>)> 
+    mut dirty: ResMut<DirtyRegion<T>>,
+    mut drawn: Local<HashMap<Entity, Rect>>,
+    changed: Query<(Entity, &Position, &Shape), (With<U>, Or<(Changed<Position>, Changed<Shape>)>)>,
+    targets: Query<(Entity, &Position, &Shape), With<U>>
 ) where T: CellStatus + 'static, U: Component{
     
-    if !targets.is_empty(){
-        map.reset(T::default());
+    let mut area: Option<Rect> = None;
+
+    // Both where the shapes were drawn and where they are now have to be drawn again
+    for (e, position, shape) in &changed {
+        let rect = shape.get_rectangle_with_center(position.value());
+
+        for rect in drawn.insert(e, rect).into_iter().chain([rect]) {
+            area = Some(area.map_or(rect, |area| area.union(rect)));
+        }
     }
-    
-    for (position, shape) in &targets {
+
+    drawn.retain(|e, rect| {
+        if targets.contains(*e) {
+            return true;
+        }
+
+        area = Some(area.map_or(*rect, |area| area.union(*rect)));
+        false
+    });
+
+    let Some(region) = area.and_then(|area| map.get_cells_overlapping(area)) else {
+        return;
+    };
+
+    for x in region.min.x..region.max.x {
+        for y in region.min.y..region.max.y {
+            let _ = map.set(IVec2::new(x, y), T::default());
+        }
+    }
+
+    // Shapes overlapping the region may cover cells that were just cleared
+    for (_, position, shape) in &targets {
         let center = position.value();
         let rect = shape.get_rectangle_with_center(center);
 
-        let region = map.get_cells_within(rect);
-        
-        let region = match region {
-            Some(v) => v,
-            None => continue,
+        let Some(cells) = map.get_cells_overlapping(rect) else {
+            continue;
         };
 
-        for x in region.min.x..region.max.x {
-            for y in region.min.y..region.max.y {
+        let cells = cells.intersect(region);
 
-                
-                let cell = IVec2::new(x, y);
-
-                if let Some(value) = map.get(&cell){
-
-                    if value == &T::get_non_default_value(){
-                        continue;
-                    }
-                }
-                else {
-                    continue;
-                }
+        for x in cells.min.x..cells.max.x {
+            for y in cells.min.y..cells.max.y {
 
-                let cell_center = map.get_coord(cell);
+                let cell = IVec2::new(x, y);
 
-                if !point_in_shape(shape, center, cell_center) {
-                    let _ = map.set(cell, T::default());
-                    continue;
+                if point_in_shape(shape, center, map.get_coord(cell)) {
+                    let _ = map.set(cell, T::get_non_default_value());
                 }
-                
-                let _ = map.set(cell, T::get_non_default_value());
             }
         }
-
-        
     }
+
+    dirty.mark(region);
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn compute_proximity_map(
     mut proximity_multi_map: ResMut<EntityMultiField<TargetProximity>>, 
     obstacles_map: Res<Field<BlockedStatus>>, 
     target_multi_map: Res<EntityMultiField<TargetStatus>>,
-    density_mutli_field: Res<EntityMultiField<AgentDensity>>){
-    
-    // if !density_map.is_changed() && !obstacles_map.is_changed() && !target_multi_map.is_changed(){
-    //     return;
-    // }
+    density_mutli_field: Res<EntityMultiField<AgentDensity>>,
+    mut dirty_obstacles: ResMut<DirtyRegion<BlockedStatus>>,
+    mut dirty_density: ResMut<DirtyRegion<AgentDensity>>,
+    mut dirty_targets: ResMut<EntityDirtyRegions<TargetStatus>>,
+    mut dirty_proximity: ResMut<EntityDirtyRegions<TargetProximity>>,
+    strategy: Res<ProximityComputationStrategy>){
 
-    for (target, proximity_map) in proximity_multi_map.iter_mut() {
-        let target_map = target_multi_map.get(target).expect("Could not find related target colision map");
-        //let density_map = density_mutli_field.get(target).expect("Could not find related density map");
+    // Obstacles and the densities of every objective are part of the distances to each objective
+    let shared_dirty = [dirty_obstacles.take(), dirty_density.take()].into_iter().flatten().reduce(|a, b| a.union(b));
 
-        let mut open_list = VecDeque::new();
+    if shared_dirty.is_none() && dirty_targets.is_empty() {
+        return;
+    }
 
-        proximity_map.reset(TargetProximity::NotComputed);
-    
-        for x in 0..proximity_map.get_columns() {
-            for y in 0..proximity_map.get_rows() {
-                let pos: IVec2 = IVec2::new(x as i32, y as i32);
-    
-                let proximity = match (obstacles_map.get(&pos), target_map.get(&pos)) {
-                    (Some(BlockedStatus::Blocked), _) => TargetProximity::Obstacle,
-                    (_, Some(TargetStatus::IsTarget)) => TargetProximity::Computed(0.),
-                    (_, _) => TargetProximity::NotComputed
-                };
-    
-                if let TargetProximity::Computed(_) = proximity{
-                    open_list.push_back(pos);
-                }
-    
-                proximity_map.set(pos, proximity).ok();
-            }
+    let obstacles_map = &*obstacles_map;
+    let density_mutli_field = &*density_mutli_field;
+    let targets_dirty = &*dirty_targets;
+    let strategy = *strategy;
+
+    // One task per objective, each field only depends on shared inputs
+    let changed = ComputeTaskPool::get().scope(|scope| {
+        for (target, proximity_map) in proximity_multi_map.iter_mut() {
+            let Some(dirty) = [shared_dirty, targets_dirty.get(target)].into_iter().flatten().reduce(|a, b| a.union(b)) else {
+                continue;
+            };
+
+            let target_map = target_multi_map.get(target).expect("Could not find related target colision map");
+
+            scope.spawn(async move {
+                (*target, compute_proximity_field(target, proximity_map, obstacles_map, target_map, density_mutli_field, strategy, dirty))
+            });
         }
-    
-        while let Some(pivot_pos) = open_list.pop_front(){
-    
-            let any_invalid_coordinate = pivot_pos.adjacent()
-                .iter()
-                .any(|coord| proximity_map.get(coord) == Some(&TargetProximity::Obstacle));
-    
-            if any_invalid_coordinate {
-                proximity_map.set(pivot_pos, TargetProximity::Buffer).unwrap();
+    });
+
+    dirty_targets.clear();
+
+    for (target, cells) in changed {
+        if let Some(cells) = cells {
+            dirty_proximity.mark(target, cells);
+        }
+    }
+}
+
+/// Computes the distances again around the `dirty` cells, returns the cells whose distance changed
+fn compute_proximity_field(
+    target: &Entity,
+    proximity_map: &mut Field<TargetProximity>,
+    obstacles_map: &Field<BlockedStatus>,
+    target_map: &Field<TargetStatus>,
+    density_mutli_field: &EntityMultiField<AgentDensity>,
+    strategy: ProximityComputationStrategy,
+    dirty: IRect) -> Option<IRect>{
+
+    let previous = proximity_map.as_vec().clone();
+    let bounds = proximity_map.get_bounds();
+
+    // Cells next to the dirty ones may turn into buffers
+    let dirty = dirty.inflate(1).intersect(bounds);
+
+    // A path through the dirty cells costs at least the distance of the cell it enters them from,
+    // so the cells closer to the target than every cell around them keep their distance
+    let mut threshold = f32::INFINITY;
+
+    let border = dirty.inflate(1).intersect(bounds);
+
+    for x in border.min.x..border.max.x {
+        for y in border.min.y..border.max.y {
+            if let Some(TargetProximity::Computed(value)) = proximity_map.get(&IVec2::new(x, y)) {
+                threshold = threshold.min(*value);
             }
-    
-            let value_pivot_pos =  proximity_map.get(&pivot_pos).cloned();
-    
-            let value_pivot_pos = match value_pivot_pos {
-                Some(TargetProximity::Computed(value)) => value,
-                _ => continue,
+        }
+    }
+
+    for x in 0..proximity_map.get_columns() {
+        for y in 0..proximity_map.get_rows() {
+            let pos: IVec2 = IVec2::new(x as i32, y as i32);
+
+            let proximity = match (obstacles_map.get(&pos), target_map.get(&pos)) {
+                (Some(BlockedStatus::Blocked), _) => TargetProximity::Obstacle,
+                (_, Some(TargetStatus::IsTarget)) => TargetProximity::Computed(0.),
+                (_, _) => match proximity_map.get(&pos) {
+                    Some(&TargetProximity::Computed(value)) if value < threshold && !contains_cell(dirty, pos) => TargetProximity::Computed(value),
+                    _ => TargetProximity::NotComputed,
+                }
             };
-    
-            for current_cell in pivot_pos.adjacent(){
-                
-                let value_at_cell = proximity_map.get(&current_cell);
-    
-                let delta = (current_cell - pivot_pos).as_vec2().length();
-                // let density_delta = density_map.get(&current_cell);
-                
-                let densities = density_mutli_field.get_all(&current_cell);
 
-                let same_target_density = densities.iter().find(|(k, _)| *k == target);
+            proximity_map.set(pos, proximity).ok();
+        }
+    }
 
-                let same_target_density = match same_target_density {
-                    Some((_, density)) => density.map_or(0., |v| v.value()),
-                    None => 0.,
-                };
+    // The distances spread from the cells that kept theirs
+    let mut seeds = Vec::new();
 
-                let other_target_density : f32 = densities.
-                iter()
-                .filter(|(k, _)| *k != target)
-                .filter_map(|(_, v)| v.to_owned())
-                .map(|x| x.value()).sum();
-
-                let base_repulsion = 20.;
-                let ratio = 0.5;
-
-                match value_at_cell {
-                    Some(TargetProximity::NotComputed) => {
-                        proximity_map.set(current_cell, TargetProximity::Computed(value_pivot_pos + delta + other_target_density * base_repulsion + same_target_density * base_repulsion * ratio)).unwrap();
-                        open_list.push_back(current_cell);
-                    },
-                    Some(TargetProximity::Computed(value)) => {
-                        let new_distance = value_pivot_pos + delta + other_target_density * base_repulsion + same_target_density * base_repulsion * ratio;
-                        let distance = f32::min(*value, new_distance);
-                        proximity_map.set(current_cell, TargetProximity::Computed(distance)).unwrap();
-                    },
-                    _ => {}
-                };
+    for x in 0..proximity_map.get_columns() {
+        for y in 0..proximity_map.get_rows() {
+            let pos: IVec2 = IVec2::new(x as i32, y as i32);
+
+            let Some(TargetProximity::Computed(_)) = proximity_map.get(&pos) else {
+                continue;
+            };
+
+            if pos.adjacent().iter().any(|coord| proximity_map.get(coord) == Some(&TargetProximity::NotComputed)) {
+                seeds.push(pos);
             }
         }
     }
 
+    let base_repulsion = 20.;
+    let ratio = 0.5;
 
-    
+    // Crowded cells cost more to go through, agents heading elsewhere more than the ones heading to the same target
+    let penalty = |cell: IVec2| {
+        let densities = density_mutli_field.get_all(&cell);
+
+        let same_target_density = densities.iter().find(|(k, _)| *k == target);
+
+        let same_target_density = match same_target_density {
+            Some((_, density)) => density.map_or(0., |v| v.value()),
+            None => 0.,
+        };
+
+        let other_target_density : f32 = densities.
+        iter()
+        .filter(|(k, _)| *k != target)
+        .filter_map(|(_, v)| v.to_owned())
+        .map(|x| x.value()).sum();
+
+        other_target_density * base_repulsion + same_target_density * base_repulsion * ratio
+    };
+
+    solve_proximity(strategy, proximity_map, seeds, penalty);
+
+    proximity_map.get_changed_cells(&previous)
+}
+
+/// Whether `cell` is within `cells`, whose `max` is exclusive
+fn contains_cell(cells: IRect, cell: IVec2) -> bool {
+    cell.cmpge(cells.min).all() && cell.cmplt(cells.max).all()
 }
 
-pub fn compute_vector_map(mut vector_multi_field: ResMut<EntityMultiField<Vec2>>, proximity_multi_map: ResMut<EntityMultiField<TargetProximity>>){
+pub fn compute_vector_map(
+    mut vector_multi_field: ResMut<EntityMultiField<Vec2>>, 
+    proximity_multi_map: Res<EntityMultiField<TargetProximity>>,
+    mut dirty_proximity: ResMut<EntityDirtyRegions<TargetProximity>>){
     
-    if !proximity_multi_map.is_changed() {
+    if dirty_proximity.is_empty() {
         return;
     }
 
-    for (entity, proximity_map) in proximity_multi_map.iter() {
+    let proximity_dirty = &*dirty_proximity;
 
-        let vector_field = vector_multi_field.get_mut(entity).expect("Grid map pot found in grid multi map");
+    ComputeTaskPool::get().scope(|scope| {
+        for (entity, vector_field) in vector_multi_field.iter_mut() {
+            let (Some(proximity_map), Some(dirty)) = (proximity_multi_map.get(entity), proximity_dirty.get(entity)) else {
+                continue;
+            };
 
-        for x_center in 0..proximity_map.get_columns(){
-            for y_center in 0..proximity_map.get_rows(){
-    
-                let center: IVec2 = IVec2::new(x_center as i32, y_center as i32);
-                let mut values = [Vec2::ZERO; 8];
-                let mut i = 0;
-    
-                let invalid_coordinate = proximity_map.get(&center) == Some(&TargetProximity::Obstacle);
-    
-                if invalid_coordinate {
-                    continue;
-                }
-    
-                for current_pos in center.adjacent(){
-    
-                    values[i] = match proximity_map.get(&current_pos) {
-                        Some(TargetProximity::Computed(value)) => 1./value * (current_pos - center).as_vec2(),
-                        _ => Vec2::ZERO,
-                    };
-                
-                    i += 1;
-                }
-    
-                let final_vector = values
-                .iter()
-                .fold(Vec2::ZERO, |acc, &v| acc + v)
-                .normalize();
-    
-                vector_field.set(center, final_vector).ok();
+            scope.spawn(async move {
+                compute_vector_field(proximity_map, vector_field, dirty);
+            });
+        }
+    });
+
+    dirty_proximity.clear();
+}
+
+/// Computes the vectors again around the `dirty` cells of the proximity field
+fn compute_vector_field(proximity_map: &Field<TargetProximity>, vector_field: &mut Field<Vec2>, dirty: IRect){
+
+    // Vectors depend on the distances of the adjacent cells
+    let cells = dirty.inflate(1).intersect(proximity_map.get_bounds());
+
+    for x_center in cells.min.x..cells.max.x{
+        for y_center in cells.min.y..cells.max.y{
+
+            let center: IVec2 = IVec2::new(x_center, y_center);
+            let mut values = [Vec2::ZERO; 8];
+            let mut i = 0;
+
+            let invalid_coordinate = proximity_map.get(&center) == Some(&TargetProximity::Obstacle);
+
+            if invalid_coordinate {
+                vector_field.set(center, Vec2::ZERO).ok();
+                continue;
+            }
+
+            for current_pos in center.adjacent(){
+
+                values[i] = match proximity_map.get(&current_pos) {
+                    Some(TargetProximity::Computed(value)) => 1./value * (current_pos - center).as_vec2(),
+                    _ => Vec2::ZERO,
+                };
+            
+                i += 1;
             }
+
+            let final_vector = values
+            .iter()
+            .fold(Vec2::ZERO, |acc, &v| acc + v)
+            .normalize();
+
+            vector_field.set(center, final_vector).ok();
         }
     }
 }
@@ -296,7 +382,7 @@ pub fn compute_vector_map(mut vector_multi_field: ResMut<EntityMultiField<Vec2>>
 pub fn compute_density_map(
     constants: Res<FlowFieldConstants>,
     mut density_mutli_field: ResMut<EntityMultiField<AgentDensity>>, 
-    agents: Query<(&Position, &Shape, &Destination), (With<Agent>, Changed<Position>)>){
+    agents: Query<(&Position, &Shape, &Destination), (With<Agent>, Or<(Changed<Position>, Changed<Destination>)>)>){
 
     density_mutli_field.reset(0.0.into());
 
@@ -351,6 +437,114 @@ pub fn compute_density_map(
     }
 }
 
+pub fn compute_voronoi_density_map(
+    cutoff: Res<VoronoiCutoff>,
+    simulation_area: Res<SimulationArea>,
+    edge_grid: Res<ObstacleEdgeGrid>,
+    mut density_mutli_field: ResMut<EntityMultiField<AgentDensity>>,
+    agents: Query<(&Position, &Destination), With<Agent>>,
+    obstacles: Query<(&Position, &Shape), With<Obstacle>>){
+
+    density_mutli_field.reset(0.0.into());
+
+    let cutoff = cutoff.0;
+    let area = simulation_area.0;
+
+    let mut grid = AgentSpatialHash::new(2. * cutoff);
+    for (position, _) in &agents {
+        grid.insert(AgentSpatialHashEntry { entity: Entity::PLACEHOLDER, position: position.value(), speed: Vec2::ZERO, radius: 0. });
+    }
+
+    for (position, destination) in &agents {
+
+        let Some(density_map) = density_mutli_field.get_mut(&destination.0) else {
+            continue;
+        };
+
+        let center = position.value();
+        let neighbours = grid.neighbours(center, 2. * cutoff).map(|entry| entry.position);
+
+        let mut cell = voronoi_cell(center, neighbours, cutoff);
+
+        for (point, normal) in [(area.min, -Vec2::X), (area.min, -Vec2::Y), (area.max, Vec2::X), (area.max, Vec2::Y)] {
+            cell = clip_polygon_to_half_plane(&cell, point, normal);
+        }
+
+        // Each obstacle within reach cuts the cell along its tangent at the point closest to the agent
+        for (obstacle, normal, distance) in edge_grid.closest_per_obstacle(center, cutoff) {
+            let normal = normal.normalize_or_zero();
+
+            if distance >= cutoff || normal == Vec2::ZERO {
+                continue;
+            }
+
+            if obstacles.get(obstacle).is_ok_and(|(position, shape)| point_in_shape(shape, position.value(), center)) {
+                continue;
+            }
+
+            cell = clip_polygon_to_half_plane(&cell, center - normal * distance, -normal);
+        }
+
+        let cell_area = polygon_signed_area(&cell).abs();
+
+        if cell_area <= 0. {
+            continue;
+        }
+
+        let (min, max) = cell.iter().fold((Vec2::MAX, Vec2::MIN), |(min, max), p| (min.min(*p), max.max(*p)));
+
+        let Some(region) = density_map.get_cells_overlapping(Rect::from_corners(min, max)) else {
+            continue;
+        };
+
+        let grid_cell_dimentions = density_map.get_cell_dimentions();
+        let grid_cell_area = grid_cell_dimentions.x * grid_cell_dimentions.y;
+
+        for x in region.min.x..region.max.x {
+            for y in region.min.y..region.max.y {
+
+                let grid_cell = IVec2::new(x, y);
+                let overlap = intersection_area(&cell, &density_map.get_cell_outline(grid_cell));
+
+                if overlap <= 0. {
+                    continue;
+                }
+
+                if let Some(value) = density_map.get(&grid_cell){
+                    let new_density = *value + (overlap / cell_area / grid_cell_area).into();
+                    density_map.set(grid_cell, new_density).unwrap();
+                }
+            }
+        }
+    }
+}
+
+/// Marks the cells whose density changed since the last time the fields were computed
+pub fn mark_density_changes(
+    density_mutli_field: Res<EntityMultiField<AgentDensity>>,
+    mut dirty: ResMut<DirtyRegion<AgentDensity>>,
+    mut previous: Local<HashMap<Entity, Vec<AgentDensity>>>){
+
+    for (entity, field) in density_mutli_field.iter() {
+        let changed = match previous.get(entity) {
+            Some(values) => field.get_changed_cells(values),
+            None => Some(field.get_bounds()),
+        };
+
+        if let Some(cells) = changed {
+            dirty.mark(cells);
+            previous.insert(*entity, field.as_vec().clone());
+        }
+    }
+
+    previous.retain(|entity, _| density_mutli_field.get(entity).is_some());
+}
+
+/// Fields are computed on the first tick and then every `recompute_interval` ticks
+pub fn recompute_fields_this_tick(time: Res<SimulationTime>, constants: Res<FlowFieldConstants>) -> bool {
+    time.ticks().is_multiple_of(constants.recompute_interval.max(1))
+}
+
 fn density_kernel(distance: f32,  radius: f32) -> f32{
     if distance >= radius {
         return 0.;
@@ -645,4 +839,296 @@ pub fn draw_density(
         }
     }
 
-}
\ No newline at end of file
+}
+
+// #######
+// Testing
+// #######
+
+#[cfg(test)]
+fn voronoi_density_app() -> (App, Entity) {
+    use crate::plugins::social_foces_model::system::rebuild_obstacle_edge_grid;
+
+    let mut app = App::new();
+
+    let area = Rect::from_center_size(Vec2::ZERO, Vec2::splat(10.));
+    let mut fields = EntityMultiField::new(10, 10, area, AgentDensity::default());
+
+    let objective = app.world_mut().spawn(Objective).id();
+    fields.ensure(objective);
+
+    app.init_resource::<VoronoiCutoff>()
+        .insert_resource(SimulationArea(area))
+        .insert_resource(ObstacleEdgeGrid::new(1.))
+        .insert_resource(fields)
+        .add_systems(Update, (rebuild_obstacle_edge_grid, compute_voronoi_density_map).chain());
+
+    (app, objective)
+}
+
+#[test]
+fn check_voronoi_density_of_a_grid_of_agents() {
+
+    // Setup
+
+    let (mut app, objective) = voronoi_density_app();
+
+    // One agent in the middle of each 1m x 1m cell
+    for x in -5..5 {
+        for y in -5..5 {
+            let position = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            app.world_mut().spawn((Agent, Position::from(position), Destination(objective)));
+        }
+    }
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    let fields = app.world().resource::<EntityMultiField<AgentDensity>>();
+    let field = fields.get(&objective).unwrap();
+
+    assert!(field.get_grid().as_vec().iter().all(|density| (density.value() - 1.).abs() < 1e-3));
+}
+
+#[test]
+fn check_voronoi_density_is_clipped_by_obstacles() {
+
+    // Setup
+
+    let (mut app, objective) = voronoi_density_app();
+
+    app.world_mut().spawn((Agent, Position::from(Vec2::new(0.5, 0.5)), Destination(objective)));
+
+    // Wall along x = 1, the agent cell stops there
+    app.world_mut().spawn((
+        Obstacle,
+        Position::from(Vec2::new(2., 0.)),
+        Shape::Polygon(vec![Vec2::new(-1., -5.), Vec2::new(1., -5.), Vec2::new(1., 5.), Vec2::new(-1., 5.)]),
+    ));
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    let fields = app.world().resource::<EntityMultiField<AgentDensity>>();
+    let field = fields.get(&objective).unwrap();
+
+    let total: f32 = field.get_grid().as_vec().iter().map(|density| density.value()).sum();
+
+    assert!((total - 1.).abs() < 1e-3);
+    assert_eq!(field.get(&IVec2::new(6, 5)).unwrap().value(), 0.);
+    assert!(field.get(&IVec2::new(5, 5)).unwrap().value() > 0.);
+}
+
+#[cfg(test)]
+fn flow_field_app() -> (App, Entity) {
+    let mut app = App::new();
+
+    let area = Rect::from_center_size(Vec2::ZERO, Vec2::splat(10.));
+
+    ComputeTaskPool::get_or_init(bevy::tasks::TaskPool::default);
+
+    app.insert_resource(FlowFieldConstants::default())
+        .insert_resource(ProximityComputationStrategy::default())
+        .insert_resource(SimulationArea(area))
+        .insert_resource(Field::new(10, 10, area, BlockedStatus::default()))
+        .insert_resource(EntityMultiField::new(10, 10, area, TargetStatus::default()))
+        .insert_resource(EntityMultiField::new(10, 10, area, TargetProximity::default()))
+        .insert_resource(EntityMultiField::new(10, 10, area, Vec2::ZERO))
+        .insert_resource(EntityMultiField::new(10, 10, area, AgentDensity::default()))
+        .init_resource::<DirtyRegion<BlockedStatus>>()
+        .init_resource::<DirtyRegion<AgentDensity>>()
+        .init_resource::<EntityDirtyRegions<TargetStatus>>()
+        .init_resource::<EntityDirtyRegions<TargetProximity>>()
+        .add_systems(Update, (
+            add_field_for_objectives::<TargetStatus>,
+            add_field_for_objectives::<TargetProximity>,
+            add_field_for_objectives::<Vec2>,
+            add_field_for_objectives::<AgentDensity>,
+            (compute_colision_map::<BlockedStatus, Obstacle>, compute_objective_colision_map, compute_density_map),
+            mark_density_changes,
+            compute_proximity_map,
+            compute_vector_map,
+        ).chain());
+
+    // Objective on the left edge of the area
+    let objective = app.world_mut().spawn((
+        Objective,
+        Position::from(Vec2::new(-4.5, 0.5)),
+        Shape::Circle(0.4),
+    )).id();
+
+    (app, objective)
+}
+
+#[cfg(test)]
+fn proximity_at(app: &App, objective: Entity, cell: IVec2) -> TargetProximity {
+    let fields = app.world().resource::<EntityMultiField<TargetProximity>>();
+
+    *fields.get(&objective).unwrap().get(&cell).unwrap()
+}
+
+#[cfg(test)]
+fn set_proximity_at(app: &mut App, objective: Entity, cell: IVec2, value: TargetProximity) {
+    let mut fields = app.world_mut().resource_mut::<EntityMultiField<TargetProximity>>();
+
+    fields.get_mut(&objective).unwrap().set(cell, value).unwrap();
+}
+
+#[test]
+fn check_moving_an_obstacle_keeps_the_others() {
+
+    // Setup
+
+    let (mut app, _) = flow_field_app();
+
+    let square = || Shape::Polygon(vec![Vec2::new(-0.5, -0.5), Vec2::new(0.5, -0.5), Vec2::new(0.5, 0.5), Vec2::new(-0.5, 0.5)]);
+
+    app.world_mut().spawn((Obstacle, Position::from(Vec2::new(-2.5, -2.5)), square()));
+    let moving = app.world_mut().spawn((Obstacle, Position::from(Vec2::new(2.5, 2.5)), square())).id();
+
+    app.update();
+
+    // Act
+
+    app.world_mut().get_mut::<Position>(moving).unwrap().set_value(Vec2::new(2.5, -2.5));
+    app.update();
+
+    // Assert
+
+    let map = app.world().resource::<Field<BlockedStatus>>();
+
+    assert_eq!(map.get(&IVec2::new(2, 2)), Some(&BlockedStatus::Blocked));
+    assert_eq!(map.get(&IVec2::new(7, 7)), Some(&BlockedStatus::Empty));
+    assert_eq!(map.get(&IVec2::new(7, 2)), Some(&BlockedStatus::Blocked));
+}
+
+#[test]
+fn check_fields_are_not_computed_again_without_changes() {
+
+    // Setup
+
+    let (mut app, objective) = flow_field_app();
+
+    app.update();
+
+    // A value the computation would never produce
+    set_proximity_at(&mut app, objective, IVec2::new(9, 9), TargetProximity::Computed(-1.));
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    assert_eq!(proximity_at(&app, objective, IVec2::new(9, 9)), TargetProximity::Computed(-1.));
+}
+
+#[test]
+fn check_only_cells_past_a_new_obstacle_are_computed_again() {
+
+    // Setup
+
+    let (mut app, objective) = flow_field_app();
+
+    app.update();
+
+    let far_cell = IVec2::new(9, 5);
+    let TargetProximity::Computed(far_distance) = proximity_at(&app, objective, far_cell) else {
+        panic!("Far cell not computed");
+    };
+
+    // Values the computation would never produce, next to the objective and past the obstacle
+    set_proximity_at(&mut app, objective, IVec2::new(1, 5), TargetProximity::Computed(-1.));
+    set_proximity_at(&mut app, objective, IVec2::new(9, 0), TargetProximity::Computed(100.));
+
+    // Act
+
+    // Wall between the objective and the right edge, open at the bottom
+    app.world_mut().spawn((
+        Obstacle,
+        Position::from(Vec2::new(2.5, 1.)),
+        Shape::Polygon(vec![Vec2::new(-0.5, -4.), Vec2::new(0.5, -4.), Vec2::new(0.5, 4.), Vec2::new(-0.5, 4.)]),
+    ));
+
+    app.update();
+
+    // Assert
+
+    assert_eq!(proximity_at(&app, objective, IVec2::new(1, 5)), TargetProximity::Computed(-1.));
+    assert_eq!(proximity_at(&app, objective, IVec2::new(7, 5)), TargetProximity::Obstacle);
+
+    let TargetProximity::Computed(new_far_distance) = proximity_at(&app, objective, far_cell) else {
+        panic!("Far cell not computed again");
+    };
+
+    assert!(new_far_distance > far_distance);
+    assert!(matches!(proximity_at(&app, objective, IVec2::new(9, 0)), TargetProximity::Computed(value) if value < 100.));
+}
+
+#[test]
+fn check_fields_are_computed_every_recompute_interval() {
+
+    // Setup
+
+    let mut app = App::new();
+
+    app.insert_resource(FlowFieldConstants { recompute_interval: 3, ..Default::default() })
+        .init_resource::<SimulationTime>();
+
+    let mut computed = Vec::new();
+
+    // Act
+
+    for _ in 0..6 {
+        computed.push(app.world_mut().run_system_cached(recompute_fields_this_tick).unwrap());
+        app.world_mut().resource_mut::<SimulationTime>().advance(0.1);
+    }
+
+    // Assert
+
+    assert_eq!(computed, [true, false, false, true, false, false]);
+}
+
+#[test]
+fn check_distances_computed_again_match_a_full_computation() {
+
+    // Setup
+
+    let wall = || (
+        Obstacle,
+        Position::from(Vec2::new(0.5, -1.)),
+        Shape::Polygon(vec![Vec2::new(-0.5, -3.), Vec2::new(0.5, -3.), Vec2::new(0.5, 3.), Vec2::new(-0.5, 3.)]),
+    );
+
+    let (mut incremental, objective) = flow_field_app();
+    incremental.update();
+
+    let (mut full, _) = flow_field_app();
+    full.world_mut().spawn(wall());
+
+    // Act
+
+    incremental.world_mut().spawn(wall());
+    incremental.update();
+
+    full.update();
+
+    // Assert
+
+    for x in 0..10 {
+        for y in 0..10 {
+            let cell = IVec2::new(x, y);
+
+            match (proximity_at(&incremental, objective, cell), proximity_at(&full, objective, cell)) {
+                (TargetProximity::Computed(a), TargetProximity::Computed(b)) => assert!((a - b).abs() < 1e-4, "{} {} {}", cell, a, b),
+                (a, b) => assert_eq!(a, b, "{}", cell),
+            }
+        }
+    }
+}
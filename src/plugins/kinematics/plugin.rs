@@ -1,11 +1,13 @@
-use bevy::{app::{Plugin, Update}, ecs::schedule::{IntoScheduleConfigs, SystemSet}};
+use bevy::{app::Plugin, ecs::schedule::{IntoScheduleConfigs, SystemSet}};
+
+use crate::plugins::simulation_clock::plugin::SimulationUpdate;
 
 use super::systems::*;
 pub struct KinematicsPlugin;
 
 impl Plugin for KinematicsPlugin {
     fn build(&self, app: &mut bevy::app::App) {
-        app.add_systems(Update, apply_velocity.in_set(KinematicsSet::ApplyVelocity));
+        app.add_systems(SimulationUpdate, apply_velocity.in_set(KinematicsSet::ApplyVelocity));
     }
 }
 
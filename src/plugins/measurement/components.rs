@@ -0,0 +1,92 @@
+use bevy::{
+    ecs::{component::Component, entity::Entity},
+    math::Vec2,
+};
+use serde::Serialize;
+
+/// Area the density, speed and flow are measured in, its outline is given by its `Shape` and `Position`
+#[derive(Component)]
+pub struct MeasurementArea;
+
+/// Line the flow is measured across, the agents crossing it are recorded in its `LineCrossings` by direction
+#[derive(Component, Clone, Copy)]
+pub struct MeasurementLine {
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+impl MeasurementLine {
+    pub fn length(&self) -> f32 {
+        self.start.distance(self.end)
+    }
+}
+
+/// Looking from the `start` of the line to its `end`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum CrossingDirection {
+    RightToLeft,
+    LeftToRight,
+}
+
+impl CrossingDirection {
+    pub const ALL: [CrossingDirection; 2] = [CrossingDirection::RightToLeft, CrossingDirection::LeftToRight];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CrossingDirection::RightToLeft => "right_to_left",
+            CrossingDirection::LeftToRight => "left_to_right",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crossing {
+    pub agent: Entity,
+
+    /// Interpolated within the tick (s)
+    pub time: f32,
+    pub direction: CrossingDirection,
+}
+
+/// Crossings of a `MeasurementLine`, the ones since the last export and the totals of the whole run
+#[derive(Component, Default)]
+pub struct LineCrossings {
+    /// Crossings not exported yet, in the order they happened
+    pub recent: Vec<Crossing>,
+
+    /// Indexed by `CrossingDirection`
+    totals: [CrossingTotals; 2],
+}
+
+#[derive(Clone, Copy, Default)]
+struct CrossingTotals {
+    count: usize,
+    first: f32, // s
+    last: f32,  // s
+}
+
+impl LineCrossings {
+    pub fn push(&mut self, crossing: Crossing) {
+        let totals = &mut self.totals[crossing.direction as usize];
+
+        if totals.count == 0 {
+            totals.first = crossing.time;
+        }
+        totals.count += 1;
+        totals.last = crossing.time;
+
+        self.recent.push(crossing);
+    }
+
+    pub fn count(&self, direction: CrossingDirection) -> usize {
+        self.totals[direction as usize].count
+    }
+
+    /// Crossings per second between the first and the last one, `None` until there are two of them (1/s)
+    pub fn flow_rate(&self, direction: CrossingDirection) -> Option<f32> {
+        let totals = self.totals[direction as usize];
+
+        (totals.count > 1 && totals.last > totals.first)
+            .then(|| (totals.count - 1) as f32 / (totals.last - totals.first))
+    }
+}
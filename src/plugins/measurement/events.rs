@@ -0,0 +1,12 @@
+use bevy::ecs::{entity::Entity, event::Event};
+
+use super::components::CrossingDirection;
+
+/// Sent when an agent crosses a `MeasurementLine`
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct LineCrossed {
+    pub line: Entity,
+    pub agent: Entity,
+    pub time: f32, // s
+    pub direction: CrossingDirection,
+}
@@ -0,0 +1,70 @@
+use bevy::ecs::entity::Entity;
+
+/// Values measured in a `MeasurementArea` during a tick
+#[derive(Clone, Debug, PartialEq)]
+pub struct AreaSample {
+    pub tick: u32,
+    pub time: f32, // s
+
+    /// The area, its name is kept in the `MeasurementSeries`
+    pub measurement: Entity,
+
+    /// Agents whose center is inside the area
+    pub agents: u32,
+
+    pub classic_density: f32, // 1/m²
+    pub voronoi_density: f32, // 1/m²
+
+    /// Mean speed of the agents inside the area (m/s)
+    pub mean_speed: f32,
+
+    /// Speed of the agents weighted by the part of their Voronoi cell inside the area (m/s)
+    pub voronoi_speed: f32,
+
+    /// Voronoi density times Voronoi speed (1/m/s)
+    pub specific_flow: f32,
+}
+
+/// Agents that crossed a `MeasurementLine` during a tick
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineSample {
+    pub tick: u32,
+    pub time: f32, // s
+
+    /// The line, its name is kept in the `MeasurementSeries`
+    pub measurement: Entity,
+
+    /// Crossings in both directions
+    pub crossings: u32,
+
+    pub flow: f32, // 1/s
+
+    /// Flow per metre of line (1/m/s)
+    pub specific_flow: f32,
+}
+
+impl AreaSample {
+    pub const COLUMNS: [&'static str; 9] = [
+        "tick", "time_s", "measurement", "agents", "classic_density_1_m2", "voronoi_density_1_m2", "mean_speed_m_s",
+        "voronoi_speed_m_s", "specific_flow_1_m_s",
+    ];
+
+    pub fn to_csv_row(&self, measurement: &str) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{}",
+            self.tick, self.time, measurement, self.agents, self.classic_density, self.voronoi_density,
+            self.mean_speed, self.voronoi_speed, self.specific_flow,
+        )
+    }
+}
+
+impl LineSample {
+    pub const COLUMNS: [&'static str; 6] = ["tick", "time_s", "measurement", "crossings", "flow_1_s", "specific_flow_1_m_s"];
+
+    pub fn to_csv_row(&self, measurement: &str) -> String {
+        format!(
+            "{},{},{},{},{},{}",
+            self.tick, self.time, measurement, self.crossings, self.flow, self.specific_flow,
+        )
+    }
+}
@@ -0,0 +1,47 @@
+use bevy::prelude::*;
+
+use crate::{
+    plugins::{
+        display::resources::DisplayConfiguration,
+        movement_tracking::plugin::TrackingSet,
+        simulation_clock::plugin::{SimulationPostUpdate, SimulationPreUpdate},
+    },
+    resources::configuration::VoronoiCutoff,
+};
+
+use super::{
+    events::LineCrossed,
+    resources::MeasurementSeries,
+    systems::*,
+};
+
+/// Measures density, speed and flow in the `MeasurementArea`s and across the `MeasurementLine`s every tick,
+/// and records the agents crossing the lines by direction, the Voronoi cells are cut off at the `VoronoiCutoff`
+///
+/// Relies on the `TrackingPlugin` for the previous positions, the output path and the export interval
+pub struct MeasurementPlugin;
+
+impl Plugin for MeasurementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VoronoiCutoff>()
+            .init_resource::<MeasurementSeries>()
+            .add_event::<LineCrossed>();
+
+        app.add_systems(SimulationPreUpdate, add_crossings_to_measurement_lines)
+            .add_systems(SimulationPostUpdate, (measure_areas, measure_lines).after(TrackingSet::Track))
+            .add_systems(
+                SimulationPostUpdate,
+                export_measurements.in_set(TrackingSet::Export).after(measure_areas).after(measure_lines),
+            )
+            .add_systems(Last, write_measurements_on_close);
+
+        app.add_systems(
+            PreUpdate,
+            add_measurement_line_labels.run_if(resource_exists::<DisplayConfiguration>),
+        )
+        .add_systems(
+            Update,
+            (draw_measurement_overlays, update_measurement_line_labels).run_if(resource_exists::<DisplayConfiguration>),
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use bevy::{
+    ecs::{entity::Entity, resource::Resource},
+    platform::collections::HashMap,
+};
+
+use crate::plugins::movement_tracking::exporters::OutputFile;
+
+use super::models::{AreaSample, LineSample};
+
+/// Samples measured since the last export, they are appended to the measurement files with the trajectories
+#[derive(Resource, Default)]
+pub struct MeasurementSeries {
+    /// Name of each measured area and line, the samples only refer to their entity
+    pub names: HashMap<Entity, String>,
+
+    pub areas: Vec<AreaSample>,
+    pub lines: Vec<LineSample>,
+
+    pub(crate) areas_file: OutputFile,
+    pub(crate) lines_file: OutputFile,
+    pub(crate) crossings_file: OutputFile,
+}
+
+impl MeasurementSeries {
+    pub fn name(&self, measurement: Entity) -> &str {
+        &self.names[&measurement]
+    }
+}
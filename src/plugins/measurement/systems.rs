@@ -0,0 +1,524 @@
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use bevy::{
+    color::palettes::tailwind::{ORANGE_500, PURPLE_500},
+    prelude::*,
+};
+
+use crate::{
+    components::prelude::*,
+    plugins::{
+        display::resources::DisplayConfiguration,
+        movement_tracking::{
+            components::PreviousPosition,
+            configuration::ExportOptions,
+            exporters::OutputFile,
+            geometry::outline,
+        },
+        social_foces_model::resources::{AgentSpatialHash, AgentSpatialHashEntry},
+        start_time::resources::StartTime,
+    },
+    resources::configuration::{SimulationConfiguration, SimulationTime, VoronoiCutoff},
+};
+
+use super::{
+    components::{Crossing, CrossingDirection, LineCrossings, MeasurementArea, MeasurementLine},
+    events::LineCrossed,
+    models::{AreaSample, LineSample},
+    resources::MeasurementSeries,
+};
+
+pub fn measure_areas(
+    cutoff: Res<VoronoiCutoff>,
+    config: Res<SimulationConfiguration>,
+    time: Res<SimulationTime>,
+    mut series: ResMut<MeasurementSeries>,
+    areas: Query<(Entity, &Shape, &Position, Option<&Name>), With<MeasurementArea>>,
+    agents: Query<(Entity, &Position, &Speed), With<Agent>>,
+) {
+    if areas.is_empty() {
+        return;
+    }
+
+    let cutoff = cutoff.0;
+
+    let mut grid = AgentSpatialHash::new(2. * cutoff);
+    for (entity, position, speed) in &agents {
+        grid.insert(AgentSpatialHashEntry { entity, position: position.value(), speed: speed.value(), radius: 0. });
+    }
+
+    for (entity, shape, position, name) in &areas {
+        let polygon = outline(shape, position.value());
+        let area = polygon_signed_area(&polygon).abs();
+
+        let (min, max) = polygon.iter().fold((Vec2::MAX, Vec2::MIN), |(min, max), p| (min.min(*p), max.max(*p)));
+        let bounds = Rect::from_corners(min - cutoff, max + cutoff);
+
+        let mut inside = 0;
+        let mut speed_sum = 0.;
+        let mut voronoi_density = 0.;
+        let mut voronoi_speed = 0.;
+
+        // Only agents within the cut off of the area can have a part of their cell in it
+        for (_, agent_position, agent_speed) in agents.iter().filter(|(_, p, _)| bounds.contains(p.value())) {
+            let (agent_position, agent_speed) = (agent_position.value(), agent_speed.value().length());
+
+            if point_in_shape(shape, position.value(), agent_position) {
+                inside += 1;
+                speed_sum += agent_speed;
+            }
+
+            let neighbours = grid.neighbours(agent_position, 2. * cutoff).map(|entry| entry.position);
+            let cell = voronoi_cell(agent_position, neighbours, cutoff);
+
+            let cell_area = polygon_signed_area(&cell).abs();
+            let overlap = intersection_area(&polygon, &cell);
+
+            if cell_area > 0. {
+                voronoi_density += overlap / cell_area;
+                voronoi_speed += overlap / cell_area * agent_speed;
+            }
+        }
+
+        // Speeds are weighted by the density of each cell, so they are divided by the total density
+        let voronoi_speed = if voronoi_density > 0. { voronoi_speed / voronoi_density } else { 0. };
+        let voronoi_density = voronoi_density / area;
+
+        series.names.entry(entity).or_insert_with(|| name.map_or(entity.to_string(), |name| name.to_string()));
+        series.areas.push(AreaSample {
+            tick: time.ticks(),
+            time: time.elapsed() + config.simulation_time_step,
+            measurement: entity,
+            agents: inside,
+            classic_density: inside as f32 / area,
+            voronoi_density,
+            mean_speed: if inside > 0 { speed_sum / inside as f32 } else { 0. },
+            voronoi_speed,
+            specific_flow: voronoi_density * voronoi_speed,
+        });
+    }
+}
+
+/// Records the agents crossing each `MeasurementLine` and the flow across it during the tick
+pub fn measure_lines(
+    config: Res<SimulationConfiguration>,
+    time: Res<SimulationTime>,
+    mut series: ResMut<MeasurementSeries>,
+    mut crossed: EventWriter<LineCrossed>,
+    mut lines: Query<(Entity, &MeasurementLine, &mut LineCrossings, Option<&Name>)>,
+    agents: Query<(Entity, &Position, &PreviousPosition), With<Agent>>,
+) {
+    for (line_entity, line, mut crossings, name) in &mut lines {
+        let before = crossings.recent.len();
+
+        for (agent, position, previous) in &agents {
+            let Some((fraction, side)) = segment_crossing(line.start, line.end, previous.value(), position.value()) else {
+                continue;
+            };
+
+            let crossing = Crossing {
+                agent,
+                time: time.elapsed() + fraction * config.simulation_time_step,
+                direction: if side > 0. { CrossingDirection::RightToLeft } else { CrossingDirection::LeftToRight },
+            };
+
+            crossings.push(crossing);
+
+            crossed.write(LineCrossed {
+                line: line_entity,
+                agent,
+                time: crossing.time,
+                direction: crossing.direction,
+            });
+        }
+
+        // The crossings are only exported after the measurements of the tick
+        let count = (crossings.recent.len() - before) as u32;
+        let flow = count as f32 / config.simulation_time_step;
+
+        series.names.entry(line_entity).or_insert_with(|| name.map_or(line_entity.to_string(), |name| name.to_string()));
+        series.lines.push(LineSample {
+            tick: time.ticks(),
+            time: time.elapsed() + config.simulation_time_step,
+            measurement: line_entity,
+            crossings: count,
+            flow,
+            specific_flow: flow / line.length(),
+        });
+    }
+}
+
+pub fn add_crossings_to_measurement_lines(
+    mut commands: Commands,
+    lines: Query<Entity, (Added<MeasurementLine>, Without<LineCrossings>)>,
+) {
+    for entity in lines.iter() {
+        commands.entity(entity).insert(LineCrossings::default());
+    }
+}
+
+/// Appends the samples measured since the last export to the measurement files, with the trajectories
+pub fn export_measurements(
+    start_time: Res<StartTime>,
+    export_options: Res<ExportOptions>,
+    mut series: ResMut<MeasurementSeries>,
+    mut crossings: Query<(Entity, &mut LineCrossings)>,
+) {
+    append_series(&export_options.path(&start_time.0), &mut series, &mut crossings);
+}
+
+pub fn write_measurements_on_close(
+    exit_events: EventReader<AppExit>,
+    start_time: Res<StartTime>,
+    export_options: Res<ExportOptions>,
+    mut series: ResMut<MeasurementSeries>,
+    mut crossings: Query<(Entity, &mut LineCrossings)>,
+) {
+    if exit_events.is_empty() {
+        return;
+    }
+
+    let trajectories = export_options.path(&start_time.0);
+
+    append_series(&trajectories, &mut series, &mut crossings);
+
+    let files = [(&series.areas_file, "areas"), (&series.lines_file, "lines"), (&series.crossings_file, "crossings")];
+    for (file, suffix) in files {
+        if file.is_created() {
+            info!("Measurements written to {}", measurement_path(&trajectories, suffix).display());
+        }
+    }
+
+    for (line, crossings) in &crossings {
+        for direction in CrossingDirection::ALL {
+            if let Some(flow_rate) = crossings.flow_rate(direction) {
+                let count = crossings.count(direction);
+                info!("{} {}: {} crossings, {:.3} 1/s", series.name(line), direction.name(), count, flow_rate);
+            }
+        }
+    }
+}
+
+/// Appends the pending samples and crossings, sorted by time, only the crossing totals stay on the lines
+fn append_series(
+    trajectories: &Path,
+    series: &mut MeasurementSeries,
+    crossings: &mut Query<(Entity, &mut LineCrossings)>,
+) {
+    let MeasurementSeries { names, areas, lines, areas_file, lines_file, crossings_file } = series;
+
+    if !areas.is_empty() {
+        let rows = areas.drain(..).map(|sample| sample.to_csv_row(&names[&sample.measurement]));
+        append_csv(areas_file, &measurement_path(trajectories, "areas"), &AreaSample::COLUMNS, rows);
+    }
+
+    if !lines.is_empty() {
+        let rows = lines.drain(..).map(|sample| sample.to_csv_row(&names[&sample.measurement]));
+        append_csv(lines_file, &measurement_path(trajectories, "lines"), &LineSample::COLUMNS, rows);
+    }
+
+    let mut recent: Vec<_> = crossings
+        .iter_mut()
+        .filter(|(_, crossings)| !crossings.recent.is_empty())
+        .flat_map(|(line, mut crossings)| std::mem::take(&mut crossings.recent).into_iter().map(move |c| (line, c)))
+        .collect();
+
+    if !recent.is_empty() {
+        recent.sort_by(|a, b| a.1.time.total_cmp(&b.1.time));
+
+        let path = measurement_path(trajectories, "crossings");
+        let rows = recent
+            .into_iter()
+            .map(|(line, c)| format!("{},{},{},{}", names[&line], c.agent, c.time, c.direction.name()));
+
+        append_csv(crossings_file, &path, &["line", "agent", "time_s", "direction"], rows);
+    }
+}
+
+/// Label showing the crossings of a `MeasurementLine` in the window
+#[derive(Component)]
+pub struct MeasurementLineLabel(pub Entity);
+
+pub fn draw_measurement_overlays(
+    config: Res<DisplayConfiguration>,
+    mut gizmos: Gizmos,
+    areas: Query<(&Shape, &Position), With<MeasurementArea>>,
+    lines: Query<&MeasurementLine>,
+) {
+    let scale = config.pixels_per_meter;
+
+    for (shape, position) in &areas {
+        let polygon = outline(shape, position.value());
+        gizmos.linestrip_2d(polygon.iter().chain(polygon.first()).map(|p| *p * scale), PURPLE_500);
+    }
+
+    for line in &lines {
+        gizmos.line_2d(line.start * scale, line.end * scale, ORANGE_500);
+
+        // Points to the left of the line, the side `RightToLeft` crossings end on
+        let middle = (line.start + line.end) / 2.;
+        let left = (line.end - line.start).perp().normalize_or_zero() * 0.5;
+        gizmos.arrow_2d(middle * scale, (middle + left) * scale, ORANGE_500);
+    }
+}
+
+pub fn add_measurement_line_labels(
+    mut commands: Commands,
+    config: Res<DisplayConfiguration>,
+    lines: Query<(Entity, &MeasurementLine), Added<MeasurementLine>>,
+) {
+    for (entity, line) in &lines {
+        // Just past the end of the line
+        let translation = (line.end + (line.end - line.start).normalize_or_zero() * 0.8) * config.pixels_per_meter;
+
+        commands.spawn((
+            MeasurementLineLabel(entity),
+            Text2d::new(""),
+            TextFont::from_font_size(12.),
+            TextColor(ORANGE_500.into()),
+            Transform::from_translation(translation.extend(1.)),
+        ));
+    }
+}
+
+pub fn update_measurement_line_labels(
+    lines: Query<&LineCrossings, Changed<LineCrossings>>,
+    mut labels: Query<(&MeasurementLineLabel, &mut Text2d)>,
+) {
+    for (label, mut text) in &mut labels {
+        if let Ok(crossings) = lines.get(label.0) {
+            text.0 = format!(
+                "R→L {} | L→R {}",
+                crossings.count(CrossingDirection::RightToLeft),
+                crossings.count(CrossingDirection::LeftToRight)
+            );
+        }
+    }
+}
+
+/// The tracking output path with a `-measurement-<suffix>` suffix and the `csv` extension
+pub fn measurement_path(trajectories: &Path, suffix: &str) -> PathBuf {
+    let stem = trajectories.file_stem().map_or("".into(), |s| s.to_string_lossy());
+
+    trajectories.with_file_name(format!("{}-measurement-{}.csv", stem, suffix))
+}
+
+/// Appends the rows, the header is only written to a new file
+fn append_csv(file: &mut OutputFile, path: &Path, columns: &[&str], rows: impl Iterator<Item = String>) {
+    let result = file.open(path).and_then(|file| {
+        let is_empty = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+
+        if is_empty {
+            writeln!(writer, "{}", columns.join(","))?;
+        }
+
+        for row in rows {
+            writeln!(writer, "{}", row)?;
+        }
+
+        writer.flush().with_context(|| format!("Failed to write {}", path.display()))
+    });
+
+    if let Err(error) = result {
+        error!("{:#}", error);
+    }
+}
+
+// #######
+// Testing
+// #######
+
+#[cfg(test)]
+fn measurement_app() -> App {
+    let mut app = App::new();
+
+    app.init_resource::<VoronoiCutoff>()
+        .insert_resource(SimulationConfiguration { simulation_time_step: 0.5 })
+        .init_resource::<SimulationTime>()
+        .init_resource::<MeasurementSeries>()
+        .add_event::<LineCrossed>()
+        .add_systems(Update, (add_crossings_to_measurement_lines, (measure_areas, measure_lines)).chain());
+
+    app
+}
+
+#[test]
+fn check_densities_of_a_grid_of_agents() {
+
+    // Setup
+
+    let mut app = measurement_app();
+
+    // Agents 1m apart moving at 1m/s, the 4m x 4m area contains 16 of them
+    for x in -5..=5 {
+        for y in -5..=5 {
+            let position = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            app.world_mut().spawn((Agent, Position::from(position), Speed::new(Vec2::new(1., 0.))));
+        }
+    }
+
+    app.world_mut().spawn((
+        MeasurementArea,
+        Name::new("center"),
+        Shape::Polygon(vec![Vec2::new(-2., -2.), Vec2::new(2., -2.), Vec2::new(2., 2.), Vec2::new(-2., 2.)]),
+        Position::from(Vec2::ZERO),
+    ));
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    let series = app.world().resource::<MeasurementSeries>();
+    let sample = &series.areas[0];
+
+    assert_eq!(series.name(sample.measurement), "center");
+    assert_eq!(sample.agents, 16);
+    assert!((sample.classic_density - 1.).abs() < 1e-4);
+    assert!((sample.voronoi_density - 1.).abs() < 1e-3);
+    assert!((sample.mean_speed - 1.).abs() < 1e-4);
+    assert!((sample.specific_flow - 1.).abs() < 1e-3);
+}
+
+#[test]
+fn check_line_crossings_are_counted() {
+
+    // Setup
+
+    let mut app = measurement_app();
+
+    app.world_mut().spawn(MeasurementLine { start: Vec2::new(0., -1.), end: Vec2::new(0., 1.) });
+
+    for (from, to) in [
+        (Vec2::new(-0.2, 0.), Vec2::new(0.2, 0.)),
+        (Vec2::new(0.2, 0.5), Vec2::new(-0.2, 0.5)),
+        (Vec2::new(-0.2, 2.), Vec2::new(0.2, 2.)),
+        (Vec2::new(-0.4, 0.), Vec2::new(-0.2, 0.)),
+    ] {
+        app.world_mut().spawn((Agent, Position::from(to), PreviousPosition::new(from)));
+    }
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    let sample = &app.world().resource::<MeasurementSeries>().lines[0];
+
+    assert_eq!(sample.crossings, 2);
+    assert_eq!(sample.flow, 4.);
+    assert_eq!(sample.specific_flow, 2.);
+}
+
+#[test]
+fn check_measurements_are_appended_at_each_export() {
+    use crate::plugins::movement_tracking::configuration::{ExportErrorPolicy, ExportFormat};
+
+    // Setup
+
+    let directory = std::env::temp_dir().join(format!("ecsmos-measurement-export-{}", std::process::id()));
+    let trajectories = directory.join("run.csv");
+
+    let mut app = measurement_app();
+
+    app.insert_resource(StartTime(chrono::Utc::now()))
+        .insert_resource(ExportOptions {
+            export_interval: 2,
+            out: trajectories.to_string_lossy().to_string(),
+            format: ExportFormat::Csv,
+            error_policy: ExportErrorPolicy::default(),
+        })
+        .add_systems(PostUpdate, export_measurements);
+
+    let line = app.world_mut()
+        .spawn((MeasurementLine { start: Vec2::new(0., -1.), end: Vec2::new(0., 1.) }, Name::new("gate")))
+        .id();
+
+    // Crosses the line again at every tick
+    app.world_mut().spawn((Agent, Position::from(Vec2::new(0.2, 0.)), PreviousPosition::new(Vec2::new(-0.2, 0.))));
+
+    // Act
+
+    app.update();
+    app.update();
+
+    let pending = app.world().resource::<MeasurementSeries>().lines.len();
+    let crossings = app.world().get::<LineCrossings>(line).unwrap();
+    let (recent, total) = (crossings.recent.len(), crossings.count(CrossingDirection::LeftToRight));
+
+    let samples = std::fs::read_to_string(measurement_path(&trajectories, "lines")).unwrap();
+    let crossed = std::fs::read_to_string(measurement_path(&trajectories, "crossings")).unwrap();
+
+    std::fs::remove_dir_all(&directory).ok();
+
+    // Assert
+
+    let samples: Vec<&str> = samples.lines().collect();
+
+    assert_eq!(pending, 0);
+    assert_eq!(samples.len(), 3);
+    assert_eq!(samples[0], LineSample::COLUMNS.join(","));
+    assert!(samples[1..].iter().all(|sample| sample.split(',').nth(2) == Some("gate")));
+
+    // Only the totals of the crossings are kept once they are exported
+    assert_eq!(recent, 0);
+    assert_eq!(total, 2);
+    assert_eq!(crossed.lines().count(), 3);
+}
+
+#[test]
+fn check_line_crossings_record_direction_and_time() {
+
+    // Setup
+
+    let mut app = measurement_app();
+
+    let line = app.world_mut().spawn(MeasurementLine { start: Vec2::new(0., -1.), end: Vec2::new(0., 1.) }).id();
+
+    // Crosses a quarter of the way through the tick, to the right of the line
+    let forward = app.world_mut().spawn((Agent, Position::from(Vec2::new(0.3, 0.)), PreviousPosition::new(Vec2::new(-0.1, 0.)))).id();
+    app.world_mut().spawn((Agent, Position::from(Vec2::new(-0.1, 0.5)), PreviousPosition::new(Vec2::new(0.1, 0.5))));
+    app.world_mut().spawn((Agent, Position::from(Vec2::new(0.1, 3.)), PreviousPosition::new(Vec2::new(-0.1, 3.))));
+
+    app.world_mut().resource_mut::<SimulationTime>().advance(2.);
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    let crossings = app.world().get::<LineCrossings>(line).unwrap();
+
+    assert_eq!(crossings.count(CrossingDirection::LeftToRight), 1);
+    assert_eq!(crossings.count(CrossingDirection::RightToLeft), 1);
+
+    let crossing = crossings.recent.iter().find(|c| c.agent == forward).unwrap();
+    assert_eq!(crossing.direction, CrossingDirection::LeftToRight);
+    assert!((crossing.time - 2.125).abs() < 1e-5);
+
+    let events = app.world().resource::<Events<LineCrossed>>();
+    assert_eq!(events.iter_current_update_events().count(), 2);
+}
+
+#[test]
+fn check_flow_rate_uses_time_between_crossings() {
+
+    // Setup
+
+    let mut crossings = LineCrossings::default();
+
+    for time in [1., 2., 3.5, 5.] {
+        crossings.push(Crossing { agent: Entity::PLACEHOLDER, time, direction: CrossingDirection::LeftToRight });
+    }
+
+    // Assert
+
+    assert_eq!(crossings.flow_rate(CrossingDirection::LeftToRight), Some(0.75));
+    assert_eq!(crossings.flow_rate(CrossingDirection::RightToLeft), None);
+}
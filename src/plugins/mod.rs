@@ -3,9 +3,13 @@ pub mod default;
 pub mod display;
 pub mod flow_field_pathfinding;
 pub mod kinematics;
+pub mod measurement;
 pub mod movement_tracking;
+pub mod report;
+pub mod scenario_loader;
 pub mod simple_objective;
 pub mod simulation_area;
+pub mod simulation_clock;
 pub mod social_foces_model;
 pub mod spawner;
 pub mod start_time;
\ No newline at end of file
@@ -22,3 +22,7 @@ impl From<Vec2> for PreviousPosition {
         PreviousPosition::new(value)
     }
 }
+
+/// Distance travelled by the agent since it was spawned (m)
+#[derive(Component, Clone, Copy, Default)]
+pub struct PathLength(pub f32);
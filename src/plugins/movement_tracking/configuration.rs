@@ -1,7 +1,156 @@
+use std::path::PathBuf;
+
 use bevy::ecs::resource::Resource;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+
+use super::exporters::*;
 
 #[derive(Resource)]
 pub struct ExportOptions{
     pub export_interval: u32,
+
+    /// `{time}` is replaced by the start time of the run, the extension of the format is added when missing
     pub out: String,
-}
\ No newline at end of file
+    pub format: ExportFormat,
+    pub error_policy: ExportErrorPolicy,
+}
+
+impl ExportOptions {
+    pub fn path(&self, start_time: &DateTime<Utc>) -> PathBuf {
+        let time = start_time.format("%Y-%m-%d-%H-%M-%S").to_string();
+        let mut path = PathBuf::from(self.out.replace("{time}", &time));
+
+        if path.extension().is_none() {
+            path.set_extension(self.format.extension());
+        }
+
+        path
+    }
+
+    /// The trajectory path with a `-geometry` suffix and the `xml` extension
+    pub fn geometry_path(&self, start_time: &DateTime<Utc>) -> PathBuf {
+        let path = self.path(start_time);
+        let stem = path.file_stem().map_or("".into(), |s| s.to_string_lossy());
+
+        path.with_file_name(format!("{}-geometry.xml", stem))
+    }
+}
+
+/// What the exporters need to know about the run besides the entries
+#[derive(Debug, Clone)]
+pub struct ExportContext {
+    /// Duration of a tick (s)
+    pub time_step: f32,
+
+    /// Name of the geometry file the trajectories refer to
+    pub geometry_file: Option<String>,
+}
+
+impl Default for ExportContext {
+    fn default() -> Self {
+        Self {
+            time_step: 0.2,
+            geometry_file: None,
+        }
+    }
+}
+
+/// Exit code of the simulation when it is aborted because the tracking output could not be written
+pub const EXIT_EXPORT_FAILED: u8 = 3;
+
+/// What happens to the simulation when the tracked entries could not be written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportErrorPolicy {
+    /// Retries the write right away up to `attempts` more times, then aborts
+    Retry { attempts: u32 },
+
+    /// Keeps the failed entries in memory and writes them before the next export, aborts once more than `max_entries` are kept.
+    /// A busy writer also makes the simulation wait once more than `max_entries` are waiting to be exported
+    Buffer { max_entries: usize },
+
+    /// Ends the simulation, the data already written is flushed
+    Abort,
+}
+
+impl ExportErrorPolicy {
+    /// Number of times a failed write is attempted again right away
+    pub fn retries(&self) -> u32 {
+        match self {
+            ExportErrorPolicy::Retry { attempts } => *attempts,
+            ExportErrorPolicy::Buffer { .. } | ExportErrorPolicy::Abort => 0,
+        }
+    }
+}
+
+impl Default for ExportErrorPolicy {
+    fn default() -> Self {
+        ExportErrorPolicy::Buffer { max_entries: 1_000_000 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ExportFormat {
+    /// Comma separated values with a header
+    #[default]
+    Csv,
+
+    /// One JSON object per line
+    JsonLines,
+
+    /// Apache Parquet, requires the `parquet` feature
+    Parquet,
+
+    /// JuPedSim/PeTrack `ID FR X Y Z` text with a geometry file
+    #[value(name = "jupedsim")]
+    JuPedSim,
+
+    /// Vadere foot steps with a geometry file
+    Vadere,
+
+    /// ETH/UCY `obsmat` layout with a geometry file
+    Eth,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::JuPedSim => "txt",
+            ExportFormat::Vadere => "traj",
+            ExportFormat::Eth => "txt",
+        }
+    }
+
+    /// Formats of the pedestrian dynamics tools come with a geometry file
+    pub fn writes_geometry(&self) -> bool {
+        matches!(self, ExportFormat::JuPedSim | ExportFormat::Vadere | ExportFormat::Eth)
+    }
+
+    /// Fails when the format is not part of this build
+    pub fn check_supported(&self) -> anyhow::Result<()> {
+        match self {
+            #[cfg(not(feature = "parquet"))]
+            ExportFormat::Parquet => anyhow::bail!("Parquet export requires building with the `parquet` feature"),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn exporter(&self, context: ExportContext) -> anyhow::Result<Box<dyn TrajectoryExporter>> {
+        self.check_supported()?;
+
+        Ok(match self {
+            ExportFormat::Csv => Box::new(CsvExporter::default()),
+            ExportFormat::JsonLines => Box::new(JsonLinesExporter::default()),
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => Box::new(ParquetExporter::default()),
+            #[cfg(not(feature = "parquet"))]
+            ExportFormat::Parquet => unreachable!(),
+            ExportFormat::JuPedSim => Box::new(JuPedSimExporter::new(context)),
+            ExportFormat::Vadere => Box::new(VadereExporter::new(context)),
+            ExportFormat::Eth => Box::new(EthExporter::default()),
+        })
+    }
+}
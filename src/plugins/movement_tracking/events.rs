@@ -0,0 +1,12 @@
+use std::path::PathBuf;
+
+use bevy::ecs::event::Event;
+
+/// Sent when the tracked entries could not be written, `entries` are the ones still waiting to be exported
+#[derive(Event, Clone, Debug)]
+pub struct ExportFailed {
+    pub path: PathBuf,
+    pub error: String,
+    pub entries: usize,
+    pub aborted: bool,
+}
@@ -0,0 +1,535 @@
+use std::collections::HashMap;
+use std::fs::{create_dir_all, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Context;
+use bevy::ecs::entity::Entity;
+
+use super::configuration::ExportContext;
+use super::resources::DataEntry;
+
+/// Writes the tracked entries to a file, `write` is called once per export with the entries since the last one
+///
+/// Exporters run on the writer thread, see `TrajectoryWriter`
+pub trait TrajectoryExporter: Send {
+    fn write(&mut self, path: &Path, entries: &[DataEntry]) -> anyhow::Result<()>;
+
+    /// Called once when the simulation ends, after the last `write`
+    fn finish(&mut self, _path: &Path) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+pub(crate) fn create_parent_directory(path: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    Ok(())
+}
+
+/// File written by an exporter, the first write replaces a file left by an earlier run and the next ones append to it
+#[derive(Default)]
+pub(crate) struct OutputFile {
+    created: bool,
+}
+
+impl OutputFile {
+    pub(crate) fn open(&mut self, path: &Path) -> anyhow::Result<File> {
+        create_parent_directory(path)?;
+
+        let file = OpenOptions::new()
+            .append(self.created)
+            .write(true)
+            .truncate(!self.created)
+            .create(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+
+        self.created = true;
+        Ok(file)
+    }
+
+    pub(crate) fn is_created(&self) -> bool {
+        self.created
+    }
+}
+
+/// Appends one line per entry, the header is only written to a new file so appending keeps it readable
+fn append_lines(
+    output: &mut OutputFile,
+    path: &Path,
+    header: &str,
+    entries: &[DataEntry],
+    mut line: impl FnMut(&mut BufWriter<File>, &DataEntry) -> std::io::Result<()>,
+) -> anyhow::Result<()> {
+    let file = output.open(path)?;
+
+    let is_empty = file.metadata()?.len() == 0;
+    let mut writer = BufWriter::new(file);
+
+    if is_empty && !header.is_empty() {
+        writeln!(writer, "{}", header)?;
+    }
+
+    for entry in entries {
+        line(&mut writer, entry)?;
+    }
+
+    writer.flush().with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Integer ids in order of first appearance starting at 1, entity indices are reused after a despawn
+#[derive(Default)]
+struct SequentialIds(HashMap<Entity, u32>);
+
+impl SequentialIds {
+    fn get(&mut self, entity: Entity) -> u32 {
+        let next = self.0.len() as u32 + 1;
+        *self.0.entry(entity).or_insert(next)
+    }
+}
+
+#[derive(Default)]
+pub struct CsvExporter {
+    output: OutputFile,
+}
+
+impl TrajectoryExporter for CsvExporter {
+    fn write(&mut self, path: &Path, entries: &[DataEntry]) -> anyhow::Result<()> {
+        append_lines(&mut self.output, path, &DataEntry::COLUMNS.join(","), entries, |writer, entry| {
+            let destination = entry.destination.map_or(String::new(), |d| d.to_string());
+
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{},{},{}",
+                entry.entity,
+                entry.tick,
+                entry.time,
+                entry.end_pos_x,
+                entry.end_pos_y,
+                entry.start_pos_x,
+                entry.start_pos_y,
+                entry.velocity_x,
+                entry.velocity_y,
+                destination,
+                entry.radius,
+            )
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct JsonLinesExporter {
+    output: OutputFile,
+}
+
+impl TrajectoryExporter for JsonLinesExporter {
+    fn write(&mut self, path: &Path, entries: &[DataEntry]) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(self.output.open(path)?);
+
+        for entry in entries {
+            serde_json::to_writer(&mut writer, entry)?;
+            writeln!(writer)?;
+        }
+
+        writer.flush().with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// JuPedSim/PeTrack `ID FR X Y Z` text, as read by jpsreport
+pub struct JuPedSimExporter {
+    context: ExportContext,
+    output: OutputFile,
+    ids: SequentialIds,
+}
+
+impl JuPedSimExporter {
+    pub fn new(context: ExportContext) -> Self {
+        Self { context, output: OutputFile::default(), ids: SequentialIds::default() }
+    }
+
+    fn header(&self) -> String {
+        let geometry = match &self.context.geometry_file {
+            Some(file) => format!("#geometry: {}\n", file),
+            None => String::new(),
+        };
+
+        format!(
+            "#description: ecsmos trajectories\n\
+             #framerate: {}\n\
+             {}\
+             #ID: the agent ID\n\
+             #FR: the current frame\n\
+             #X,Y,Z: the agent coordinates (in metres)\n\
+             \n\
+             #ID\tFR\tX\tY\tZ",
+            1. / self.context.time_step,
+            geometry,
+        )
+    }
+}
+
+impl TrajectoryExporter for JuPedSimExporter {
+    fn write(&mut self, path: &Path, entries: &[DataEntry]) -> anyhow::Result<()> {
+        let header = self.header();
+        let ids = &mut self.ids;
+
+        append_lines(&mut self.output, path, &header, entries, |writer, entry| {
+            writeln!(
+                writer,
+                "{}\t{}\t{:.4}\t{:.4}\t0",
+                ids.get(entry.entity),
+                entry.tick,
+                entry.end_pos_x,
+                entry.end_pos_y,
+            )
+        })
+    }
+}
+
+/// Vadere foot steps, one per agent and tick with its start and end
+pub struct VadereExporter {
+    context: ExportContext,
+    output: OutputFile,
+    ids: SequentialIds,
+    targets: SequentialIds,
+}
+
+impl VadereExporter {
+    pub fn new(context: ExportContext) -> Self {
+        Self {
+            context,
+            output: OutputFile::default(),
+            ids: SequentialIds::default(),
+            targets: SequentialIds::default(),
+        }
+    }
+}
+
+impl TrajectoryExporter for VadereExporter {
+    fn write(&mut self, path: &Path, entries: &[DataEntry]) -> anyhow::Result<()> {
+        let header = "pedestrianId simTime endTime-PID1 startX-PID1 startY-PID1 endX-PID1 endY-PID1 targetId-PID2";
+        let time_step = self.context.time_step;
+        let (ids, targets) = (&mut self.ids, &mut self.targets);
+
+        append_lines(&mut self.output, path, header, entries, |writer, entry| {
+            let target = entry.destination.map_or(-1, |d| targets.get(d) as i64);
+
+            writeln!(
+                writer,
+                "{} {} {} {} {} {} {} {}",
+                ids.get(entry.entity),
+                entry.time - time_step,
+                entry.time,
+                entry.start_pos_x,
+                entry.start_pos_y,
+                entry.end_pos_x,
+                entry.end_pos_y,
+                target,
+            )
+        })
+    }
+}
+
+/// ETH/UCY `obsmat` layout, `frame id x z y vx vz vy` without a header where z is the unused height
+#[derive(Default)]
+pub struct EthExporter {
+    output: OutputFile,
+    ids: SequentialIds,
+}
+
+impl TrajectoryExporter for EthExporter {
+    fn write(&mut self, path: &Path, entries: &[DataEntry]) -> anyhow::Result<()> {
+        let ids = &mut self.ids;
+
+        append_lines(&mut self.output, path, "", entries, |writer, entry| {
+            writeln!(
+                writer,
+                "{} {} {} 0 {} {} 0 {}",
+                entry.tick,
+                ids.get(entry.entity),
+                entry.end_pos_x,
+                entry.end_pos_y,
+                entry.velocity_x,
+                entry.velocity_y,
+            )
+        })
+    }
+}
+
+#[cfg(feature = "parquet")]
+pub use parquet_exporter::ParquetExporter;
+
+#[cfg(feature = "parquet")]
+mod parquet_exporter {
+    use std::{fs::File, path::Path, sync::Arc};
+
+    use anyhow::Context;
+    use arrow_array::{ArrayRef, Float32Array, RecordBatch, StringArray, UInt32Array};
+    use arrow_schema::{DataType, Field, Schema, SchemaRef};
+    use parquet::arrow::ArrowWriter;
+
+    use super::{create_parent_directory, DataEntry, TrajectoryExporter};
+
+    /// Keeps the file open for the whole run, each export becomes a row group
+    #[derive(Default)]
+    pub struct ParquetExporter {
+        writer: Option<ArrowWriter<File>>,
+    }
+
+    fn schema() -> SchemaRef {
+        let float = |name| Field::new(name, DataType::Float32, false);
+
+        Arc::new(Schema::new(vec![
+            Field::new("agent", DataType::Utf8, false),
+            Field::new("tick", DataType::UInt32, false),
+            float("time_s"),
+            float("x_m"),
+            float("y_m"),
+            float("previous_x_m"),
+            float("previous_y_m"),
+            float("vx_m_s"),
+            float("vy_m_s"),
+            Field::new("destination", DataType::Utf8, true),
+            float("radius_m"),
+        ]))
+    }
+
+    fn record_batch(schema: SchemaRef, entries: &[DataEntry]) -> anyhow::Result<RecordBatch> {
+        let float = |value: fn(&DataEntry) -> f32| -> ArrayRef {
+            Arc::new(entries.iter().map(value).collect::<Float32Array>())
+        };
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(entries.iter().map(|e| Some(e.entity.to_string())).collect::<StringArray>()),
+            Arc::new(entries.iter().map(|e| e.tick).collect::<UInt32Array>()),
+            float(|e| e.time),
+            float(|e| e.end_pos_x),
+            float(|e| e.end_pos_y),
+            float(|e| e.start_pos_x),
+            float(|e| e.start_pos_y),
+            float(|e| e.velocity_x),
+            float(|e| e.velocity_y),
+            Arc::new(entries.iter().map(|e| e.destination.map(|d| d.to_string())).collect::<StringArray>()),
+            float(|e| e.radius),
+        ];
+
+        Ok(RecordBatch::try_new(schema, columns)?)
+    }
+
+    impl TrajectoryExporter for ParquetExporter {
+        fn write(&mut self, path: &Path, entries: &[DataEntry]) -> anyhow::Result<()> {
+            if self.writer.is_none() {
+                create_parent_directory(path)?;
+                let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+                self.writer = Some(ArrowWriter::try_new(file, schema(), None)?);
+            }
+
+            let writer = self.writer.as_mut().unwrap();
+
+            writer.write(&record_batch(schema(), entries)?)?;
+            writer.flush().with_context(|| format!("Failed to write {}", path.display()))
+        }
+
+        fn finish(&mut self, path: &Path) -> anyhow::Result<()> {
+            if let Some(writer) = self.writer.take() {
+                writer.close().with_context(|| format!("Failed to close {}", path.display()))?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+// #######
+// Testing
+// #######
+
+#[cfg(test)]
+pub(crate) fn test_entry(tick: u32) -> DataEntry {
+    DataEntry {
+        entity: Entity::from_raw(3),
+        tick,
+        time: tick as f32 * 0.5,
+        end_pos_x: 1.5,
+        end_pos_y: 2.,
+        start_pos_x: 1.,
+        start_pos_y: 2.,
+        velocity_x: 1.,
+        velocity_y: 0.,
+        destination: None,
+        radius: 0.3,
+    }
+}
+
+#[test]
+fn check_csv_header_is_written_once() {
+
+    // Setup
+
+    let path = std::env::temp_dir().join(format!("ecsmos-csv-{}.csv", std::process::id()));
+    let mut exporter = CsvExporter::default();
+
+    // Act
+
+    exporter.write(&path, &[test_entry(1)]).unwrap();
+    exporter.write(&path, &[test_entry(2)]).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    // Assert
+
+    let lines: Vec<&str> = content.lines().collect();
+
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "agent,tick,time_s,x_m,y_m,previous_x_m,previous_y_m,vx_m_s,vy_m_s,destination,radius_m");
+    assert_eq!(lines[1], "3v1,1,0.5,1.5,2,1,2,1,0,,0.3");
+    assert_eq!(lines[2].split(',').count(), DataEntry::COLUMNS.len());
+}
+
+#[test]
+fn check_json_lines_use_column_names() {
+
+    // Setup
+
+    let path = std::env::temp_dir().join(format!("ecsmos-jsonl-{}.jsonl", std::process::id()));
+
+    // Act
+
+    JsonLinesExporter::default().write(&path, &[test_entry(1), test_entry(2)]).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    // Assert
+
+    let lines: Vec<serde_json::Value> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+    assert_eq!(lines.len(), 2);
+    for column in DataEntry::COLUMNS {
+        assert!(lines[0].get(column).is_some(), "missing {}", column);
+    }
+    assert_eq!(lines[1]["tick"], 2);
+    assert_eq!(lines[1]["destination"], serde_json::Value::Null);
+}
+
+#[test]
+fn check_jupedsim_header_and_sequential_ids() {
+
+    // Setup
+
+    let path = std::env::temp_dir().join(format!("ecsmos-jupedsim-{}.txt", std::process::id()));
+    let mut exporter = JuPedSimExporter::new(ExportContext {
+        time_step: 0.1,
+        geometry_file: Some("run-geometry.xml".to_string()),
+    });
+
+    let mut other = test_entry(1);
+    other.entity = Entity::from_raw(9);
+
+    // Act
+
+    exporter.write(&path, &[test_entry(1), other]).unwrap();
+    exporter.write(&path, &[test_entry(2)]).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    // Assert
+
+    assert!(content.contains("#framerate: 10\n"));
+    assert!(content.contains("#geometry: run-geometry.xml\n"));
+
+    let rows: Vec<&str> = content.lines().filter(|line| !line.starts_with('#') && !line.is_empty()).collect();
+
+    assert_eq!(rows, ["1\t1\t1.5000\t2.0000\t0", "2\t1\t1.5000\t2.0000\t0", "1\t2\t1.5000\t2.0000\t0"]);
+}
+
+#[test]
+fn check_vadere_foot_steps_span_one_tick() {
+
+    // Setup
+
+    let path = std::env::temp_dir().join(format!("ecsmos-vadere-{}.traj", std::process::id()));
+    let mut exporter = VadereExporter::new(ExportContext { time_step: 0.5, geometry_file: None });
+
+    let mut entry = test_entry(4);
+    entry.destination = Some(Entity::from_raw(7));
+
+    // Act
+
+    exporter.write(&path, &[entry]).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    // Assert
+
+    let lines: Vec<&str> = content.lines().collect();
+
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("pedestrianId simTime endTime-PID1"));
+    assert_eq!(lines[1], "1 1.5 2 1 2 1.5 2 1");
+}
+
+#[test]
+fn check_eth_rows_without_header() {
+
+    // Setup
+
+    let path = std::env::temp_dir().join(format!("ecsmos-eth-{}.txt", std::process::id()));
+    let mut exporter = EthExporter::default();
+
+    let mut other = test_entry(1);
+    other.entity = Entity::from_raw(9);
+    other.velocity_y = -0.5;
+
+    // Act
+
+    exporter.write(&path, &[test_entry(1), other]).unwrap();
+    exporter.write(&path, &[test_entry(2)]).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    // Assert
+
+    let lines: Vec<&str> = content.lines().collect();
+
+    assert_eq!(lines, ["1 1 1.5 0 2 1 0 0", "1 2 1.5 0 2 1 0 -0.5", "2 1 1.5 0 2 1 0 0"]);
+}
+
+#[test]
+fn check_rerun_replaces_the_previous_file() {
+
+    // Setup
+
+    let path = std::env::temp_dir().join(format!("ecsmos-rerun-{}.txt", std::process::id()));
+    let context = ExportContext { time_step: 0.1, geometry_file: None };
+
+    let mut other = test_entry(1);
+    other.entity = Entity::from_raw(9);
+
+    // Act
+
+    JuPedSimExporter::new(context.clone()).write(&path, &[test_entry(1), other]).unwrap();
+
+    let mut rerun = JuPedSimExporter::new(context);
+    rerun.write(&path, &[other]).unwrap();
+    rerun.write(&path, &[test_entry(2)]).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    // Assert
+
+    assert!(content.starts_with("#description: ecsmos trajectories\n"));
+    assert_eq!(content.matches("#description").count(), 1);
+
+    let rows: Vec<&str> = content.lines().filter(|line| !line.starts_with('#') && !line.is_empty()).collect();
+
+    assert_eq!(rows, ["1\t1\t1.5000\t2.0000\t0", "2\t2\t1.5000\t2.0000\t0"]);
+}
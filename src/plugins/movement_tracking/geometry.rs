@@ -0,0 +1,88 @@
+use std::f32::consts::TAU;
+use std::fmt::Write;
+
+use bevy::math::{Rect, Vec2};
+
+use crate::components::physics::Shape;
+
+/// Number of vertices circles are approximated with
+const CIRCLE_VERTICES: usize = 16;
+
+/// Vertices of the shape in world coordinates, counterclockwise
+pub fn outline(shape: &Shape, position: Vec2) -> Vec<Vec2> {
+    match shape {
+        Shape::Circle(radius) => (0..CIRCLE_VERTICES)
+            .map(|i| position + Vec2::from_angle(TAU * i as f32 / CIRCLE_VERTICES as f32) * *radius)
+            .collect(),
+        Shape::Polygon(points) => points.iter().map(|point| position + *point).collect(),
+    }
+}
+
+/// JuPedSim geometry with the simulation area as the walls of a single room and the obstacles inside it
+pub fn geometry_xml(area: Option<Rect>, obstacles: &[Vec<Vec2>]) -> String {
+    let mut xml = String::new();
+
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n");
+    xml.push_str("<geometry version=\"0.8\" caption=\"ecsmos\" unit=\"m\">\n");
+    xml.push_str("  <rooms>\n");
+    xml.push_str("    <room id=\"0\" caption=\"simulation area\">\n");
+    xml.push_str("      <subroom id=\"0\" closed=\"0\" class=\"subroom\">\n");
+
+    if let Some(area) = area {
+        let corners = [area.min, Vec2::new(area.max.x, area.min.y), area.max, Vec2::new(area.min.x, area.max.y)];
+
+        xml.push_str("        <polygon caption=\"wall\">\n");
+        push_vertices(&mut xml, &corners, "          ");
+        xml.push_str("        </polygon>\n");
+    }
+
+    for (id, obstacle) in obstacles.iter().enumerate() {
+        let _ = writeln!(xml, "        <obstacle id=\"{}\" caption=\"obstacle\" height=\"1.0\">", id);
+        xml.push_str("          <polygon>\n");
+        push_vertices(&mut xml, obstacle, "            ");
+        xml.push_str("          </polygon>\n");
+        xml.push_str("        </obstacle>\n");
+    }
+
+    xml.push_str("      </subroom>\n");
+    xml.push_str("    </room>\n");
+    xml.push_str("  </rooms>\n");
+    xml.push_str("  <transitions/>\n");
+    xml.push_str("</geometry>\n");
+
+    xml
+}
+
+/// Polygons are closed by repeating the first vertex, unless they already are
+fn push_vertices(xml: &mut String, vertices: &[Vec2], indent: &str) {
+    let closing = vertices.first().filter(|first| vertices.last() != Some(*first));
+
+    for vertex in vertices.iter().chain(closing) {
+        let _ = writeln!(xml, "{}<vertex px=\"{:.4}\" py=\"{:.4}\"/>", indent, vertex.x, vertex.y);
+    }
+}
+
+// #######
+// Testing
+// #######
+
+#[test]
+fn check_geometry_contains_area_and_obstacles() {
+
+    // Setup
+
+    let area = Rect::new(-10., -5., 10., 5.);
+    let obstacle = outline(&Shape::Polygon(vec![Vec2::new(-1., -1.), Vec2::new(1., -1.), Vec2::new(0., 1.)]), Vec2::new(2., 0.));
+
+    // Act
+
+    let xml = geometry_xml(Some(area), &[obstacle]);
+
+    // Assert
+
+    assert_eq!(xml.matches("<polygon").count(), 2);
+    assert_eq!(xml.matches("<vertex").count(), 5 + 4);
+    assert!(xml.contains("<vertex px=\"10.0000\" py=\"-5.0000\"/>"));
+    assert!(xml.contains("<vertex px=\"1.0000\" py=\"-1.0000\"/>"));
+    assert!(xml.contains("<obstacle id=\"0\""));
+}
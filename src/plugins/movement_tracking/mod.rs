@@ -2,4 +2,8 @@ pub mod plugin;
 pub mod systems;
 pub mod resources;
 pub mod components;
-pub mod configuration;
\ No newline at end of file
+pub mod configuration;
+pub mod exporters;
+pub mod events;
+pub mod writer;
+pub mod geometry;
\ No newline at end of file
@@ -1,18 +1,33 @@
 use bevy::prelude::*;
 
+use crate::plugins::{
+    kinematics::plugin::KinematicsSet,
+    simple_objective::systems::check_if_agent_arrived_at_destination,
+    simulation_area::systems::clamp_agent_position,
+    simulation_clock::plugin::{SimulationPostUpdate, SimulationPreUpdate, SimulationUpdate},
+};
 
-use super::{configuration::ExportOptions, resources::DataEntryStore, systems::*};
+use super::{
+    configuration::{ExportErrorPolicy, ExportFormat, ExportOptions},
+    events::ExportFailed,
+    resources::DataEntryStore,
+    systems::*,
+};
 
 pub struct TrackingPlugin {
     pub export_interval: u32,
     pub out: String,
+    pub format: ExportFormat,
+    pub error_policy: ExportErrorPolicy,
 }
 
 impl Default for TrackingPlugin {
     fn default() -> Self {
         Self {
             export_interval: 512,
-            out: "./out/{time}.txt".to_string()
+            out: "./out/{time}".to_string(),
+            format: ExportFormat::default(),
+            error_policy: ExportErrorPolicy::default(),
         }
     }
 }
@@ -23,24 +38,39 @@ impl Plugin for TrackingPlugin {
         app.insert_resource(ExportOptions {
             export_interval: self.export_interval,
             out: self.out.to_string(),
+            format: self.format,
+            error_policy: self.error_policy,
         });
 
+        app.add_event::<ExportFailed>()
+            .add_systems(Startup, start_trajectory_writer);
+
+        if self.format.writes_geometry() {
+            app.add_systems(SimulationPostUpdate, export_geometry.run_if(run_once));
+        }
+
         app.configure_sets(
-            Update,
+            SimulationPreUpdate,
             TrackingSet::Track.after(add_previous_position_component_to_agents),
         )
         .configure_sets(
-            PostUpdate,
+            SimulationPostUpdate,
             TrackingSet::Export
                 .after(TrackingSet::Track)
-                .run_if(run_every_n_frames),
+                .run_if(run_every_n_ticks),
         );
 
         app.insert_resource(DataEntryStore::new())
-            .add_systems(PreUpdate, add_previous_position_component_to_agents)
-            .add_systems(PreUpdate, record_previous_speed.in_set(TrackingSet::Track))
-            .add_systems(PostUpdate, track_agents.in_set(TrackingSet::Track))
-            .add_systems(PostUpdate, export_data.in_set(TrackingSet::Export))
+            .add_systems(SimulationPreUpdate, add_previous_position_component_to_agents)
+            .add_systems(SimulationPreUpdate, record_previous_speed.in_set(TrackingSet::Track))
+            // Once the position is clamped to the simulation area, before arrivals so they see the last step
+            .add_systems(SimulationUpdate, accumulate_path_length
+                .after(KinematicsSet::ApplyVelocity)
+                .after(clamp_agent_position)
+                .before(check_if_agent_arrived_at_destination))
+            .add_systems(SimulationPostUpdate, track_agents.in_set(TrackingSet::Track))
+            .add_systems(SimulationPostUpdate, collect_export_failures.after(TrackingSet::Track).before(TrackingSet::Export))
+            .add_systems(SimulationPostUpdate, export_data.in_set(TrackingSet::Export))
             .add_systems(Last, export_data_on_close);
     }
 }
@@ -1,29 +1,68 @@
-use std::fmt::Display;
-
 use bevy::{
     ecs::{entity::Entity, resource::Resource},
     math::Vec2,
 };
+use serde::{Serialize, Serializer};
 
+/// State of an agent at the end of a tick
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
 pub struct DataEntry {
+    #[serde(rename = "agent", serialize_with = "serialize_entity")]
     pub entity: Entity,
-    pub start_pos: Vec2,
-    pub end_pos: Vec2,
-    pub frame: u32,
+    pub tick: u32,
+
+    #[serde(rename = "time_s")]
+    pub time: f32,
+
+    #[serde(rename = "x_m")]
+    pub end_pos_x: f32,
+    #[serde(rename = "y_m")]
+    pub end_pos_y: f32,
+
+    #[serde(rename = "previous_x_m")]
+    pub start_pos_x: f32,
+    #[serde(rename = "previous_y_m")]
+    pub start_pos_y: f32,
+
+    #[serde(rename = "vx_m_s")]
+    pub velocity_x: f32,
+    #[serde(rename = "vy_m_s")]
+    pub velocity_y: f32,
+
+    #[serde(serialize_with = "serialize_optional_entity")]
+    pub destination: Option<Entity>,
+
+    #[serde(rename = "radius_m")]
+    pub radius: f32,
+}
+
+impl DataEntry {
+    /// Column names, in the order of the fields
+    pub const COLUMNS: [&'static str; 11] = [
+        "agent", "tick", "time_s", "x_m", "y_m", "previous_x_m", "previous_y_m", "vx_m_s", "vy_m_s", "destination", "radius_m",
+    ];
+
+    pub fn end_pos(&self) -> Vec2 {
+        Vec2::new(self.end_pos_x, self.end_pos_y)
+    }
+
+    pub fn start_pos(&self) -> Vec2 {
+        Vec2::new(self.start_pos_x, self.start_pos_y)
+    }
+
+    pub fn velocity(&self) -> Vec2 {
+        Vec2::new(self.velocity_x, self.velocity_y)
+    }
+}
+
+fn serialize_entity<S: Serializer>(entity: &Entity, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(entity)
 }
 
-impl Display for DataEntry {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} {} {} {} {} {}",
-            self.entity,
-            self.frame,
-            self.end_pos.x,
-            self.end_pos.y,
-            self.start_pos.x,
-            self.start_pos.y
-        )
+fn serialize_optional_entity<S: Serializer>(entity: &Option<Entity>, serializer: S) -> Result<S::Ok, S::Error> {
+    match entity {
+        Some(entity) => serializer.collect_str(entity),
+        None => serializer.serialize_none(),
     }
 }
 
@@ -46,13 +85,19 @@ impl DataEntryStore {
     pub fn len(&self) -> usize {
         self.0.len()
     }
-}
 
-impl Display for DataEntryStore {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for entry in &self.0 {
-            writeln!(f, "{}", entry)?;
-        }
-        Ok(())
+    pub fn entries(&self) -> &[DataEntry] {
+        &self.0
+    }
+
+    /// Empties the store and returns its entries
+    pub fn take(&mut self) -> Vec<DataEntry> {
+        std::mem::take(&mut self.0)
+    }
+
+    /// Puts back entries that could not be exported, before the ones tracked since
+    pub fn restore(&mut self, mut entries: Vec<DataEntry>) {
+        entries.append(&mut self.0);
+        self.0 = entries;
     }
 }
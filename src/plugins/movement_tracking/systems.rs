@@ -1,54 +1,195 @@
 use crate::components::prelude::*;
+use crate::plugins::simulation_area::resources::SimulationArea;
 use crate::plugins::start_time::resources::StartTime;
-use bevy::diagnostic::FrameCount;
+use crate::resources::configuration::{SimulationConfiguration, SimulationTime};
+use anyhow::Context;
 use bevy::prelude::*;
-use std::fs::{create_dir_all, OpenOptions};
-use std::io::Write;
-use std::path::Path;
 
-use super::components::PreviousPosition;
-use super::configuration::ExportOptions;
+use super::components::{PathLength, PreviousPosition};
+use super::configuration::{ExportContext, ExportErrorPolicy, ExportOptions, EXIT_EXPORT_FAILED};
+use super::events::ExportFailed;
+use super::exporters::create_parent_directory;
+use super::geometry::{geometry_xml, outline};
 use super::resources::{DataEntry, DataEntryStore};
+use super::writer::{TrajectoryWriter, WriterFailure};
 
 pub fn track_agents(
     mut store: ResMut<DataEntryStore>,
-    frame: Res<FrameCount>,
-    agents: Query<(Entity, &Position, &PreviousPosition), With<Agent>>,
+    config: Res<SimulationConfiguration>,
+    time: Res<SimulationTime>,
+    agents: Query<(Entity, &Position, &PreviousPosition, &Speed, Option<&Destination>, Option<&Shape>), With<Agent>>,
 ) {
-    for (entity, position, previous) in agents.iter() {
+    for (entity, position, previous, speed, destination, shape) in agents.iter() {
+        let radius = match shape {
+            Some(Shape::Circle(radius)) => *radius,
+            _ => 0.,
+        };
+
         let entry = DataEntry {
             entity,
-            start_pos: previous.value(),
-            end_pos: position.value(),
-            frame: frame.0,
+            tick: time.ticks(),
+            // The clock is only advanced after the tick, the position is the one at its end
+            time: time.elapsed() + config.simulation_time_step,
+            end_pos_x: position.value().x,
+            end_pos_y: position.value().y,
+            start_pos_x: previous.value().x,
+            start_pos_y: previous.value().y,
+            velocity_x: speed.value().x,
+            velocity_y: speed.value().y,
+            destination: destination.map(|d| d.0),
+            radius,
         };
 
         store.add(entry);
     }
 }
 
+/// The exporter needs the time step of the scenario, which is only known once every plugin is built
+pub fn start_trajectory_writer(
+    mut commands: Commands,
+    start_time: Res<StartTime>,
+    config: Res<ExportOptions>,
+    simulation: Res<SimulationConfiguration>,
+) {
+    let geometry_file = config
+        .format
+        .writes_geometry()
+        .then(|| config.geometry_path(&start_time.0))
+        .and_then(|path| path.file_name().map(|name| name.to_string_lossy().to_string()));
+
+    let context = ExportContext {
+        time_step: simulation.simulation_time_step,
+        geometry_file,
+    };
+
+    // The command line reports an unavailable format before the app runs, a plugin configured with one panics here
+    let exporter = config.format.exporter(context).expect("Unsupported export format");
+
+    commands.insert_resource(TrajectoryWriter::spawn(exporter, config.error_policy.retries()));
+}
+
+/// Writes the simulation area and the obstacles next to the trajectories, once the scenario is spawned
+pub fn export_geometry(
+    start_time: Res<StartTime>,
+    config: Res<ExportOptions>,
+    simulation_area: Option<Res<SimulationArea>>,
+    obstacles: Query<(&Shape, &Position), With<Obstacle>>,
+    mut failures: EventWriter<ExportFailed>,
+) {
+    let outlines: Vec<_> = obstacles.iter().map(|(shape, position)| outline(shape, position.value())).collect();
+    let xml = geometry_xml(simulation_area.map(|area| area.0), &outlines);
+
+    let path = config.geometry_path(&start_time.0);
+
+    let result = create_parent_directory(&path)
+        .and_then(|()| std::fs::write(&path, xml).with_context(|| format!("Failed to write {}", path.display())));
+
+    if let Err(error) = result {
+        error!("{:#}", error);
+
+        failures.write(ExportFailed {
+            path,
+            error: format!("{:#}", error),
+            entries: 0,
+            aborted: false,
+        });
+    }
+}
+
+/// Hands the tracked entries over to the writer thread
 pub fn export_data(
     start_time: Res<StartTime>,
     config: Res<ExportOptions>,
+    writer: Res<TrajectoryWriter>,
     mut store: ResMut<DataEntryStore>,
 ) {
-    let time = start_time.0.format("%Y-%m-%d-%H-%M-%S").to_string();
+    if writer.aborted || store.len() == 0 {
+        return;
+    }
+
+    let path = config.path(&start_time.0);
+
+    // When the writer is behind the entries wait in the store for the next export, or the simulation waits once the buffer is full
+    let result = match config.error_policy {
+        ExportErrorPolicy::Buffer { max_entries } if store.len() > max_entries => writer.write(path, store.take()),
+        _ => writer.try_write(path, store.take()),
+    };
+
+    if let Err(entries) = result {
+        debug!("Trajectory writer busy, keeping {} entries", entries.len());
+        store.restore(entries);
+    }
+}
+
+/// Applies the error policy to the writes that failed on the writer thread
+pub fn collect_export_failures(
+    config: Res<ExportOptions>,
+    mut writer: ResMut<TrajectoryWriter>,
+    mut failures: EventWriter<ExportFailed>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    for failure in writer.failures() {
+        // The writer keeps the failed entries, the last export when the app exits tries them again
+        let (path, error, entries) = match failure {
+            WriterFailure::Write { path, error, pending } => (path, error, pending),
+            WriterFailure::Finish { path, error } => (path, error, 0),
+        };
+
+        let aborted = match config.error_policy {
+            ExportErrorPolicy::Buffer { max_entries } => entries > max_entries,
+            ExportErrorPolicy::Retry { .. } | ExportErrorPolicy::Abort => true,
+        };
+
+        error!("{:#}", error);
 
-    let path = config.out.replace("{time}", &time);
-    write_data_and_clear_store(&path, &mut store);
+        if aborted && !writer.aborted {
+            error!("Aborting the simulation, {} tracked entries could not be exported", entries);
+            writer.aborted = true;
+            app_exit_events.write(AppExit::from_code(EXIT_EXPORT_FAILED));
+        }
+
+        failures.write(ExportFailed {
+            path,
+            error: format!("{:#}", error),
+            entries,
+            aborted,
+        });
+    }
 }
 
+/// Writes the last entries and waits for the writer thread to finish the file
 pub fn export_data_on_close(
     exit_events: EventReader<AppExit>,
     start_time: Res<StartTime>,
     config: Res<ExportOptions>,
+    mut writer: ResMut<TrajectoryWriter>,
     mut store: ResMut<DataEntryStore>,
+    mut failures: EventWriter<ExportFailed>,
 ) {
-    if exit_events.len() > 0 {
-        let time = start_time.0.format("%Y-%m-%d-%H-%M-%S").to_string();
+    if exit_events.is_empty() {
+        return;
+    }
+
+    // Writes that failed but were not collected yet get a last chance with the last entries
+    writer.failures();
+
+    let path = config.path(&start_time.0);
+
+    // Whatever was written so far is flushed even if the last entries are lost
+    for failure in writer.close(&path, store.take()) {
+        let (path, error, entries) = match failure {
+            WriterFailure::Write { path, error, pending } => (path, error, pending),
+            WriterFailure::Finish { path, error } => (path, error, 0),
+        };
+
+        error!("{:#}, {} tracked entries are lost", error, entries);
 
-        let path = config.out.replace("{time}", &time);
-        write_data_and_clear_store(&path, &mut store);
+        failures.write(ExportFailed {
+            path,
+            error: format!("{:#}", error),
+            entries,
+            aborted: false,
+        });
     }
 }
 
@@ -60,6 +201,14 @@ pub fn record_previous_speed(
     }
 }
 
+pub fn accumulate_path_length(
+    mut agents: Query<(&Position, &PreviousPosition, &mut PathLength), (With<Agent>, Changed<Position>)>,
+) {
+    for (position, previous, mut path_length) in agents.iter_mut() {
+        path_length.0 += position.value().distance(previous.value());
+    }
+}
+
 pub fn add_previous_position_component_to_agents(
     mut commands: Commands,
     agents: Query<(Entity, &Position), (Added<Agent>, Without<PreviousPosition>)>,
@@ -67,35 +216,309 @@ pub fn add_previous_position_component_to_agents(
     for (entity, position) in agents.iter() {
         commands
             .entity(entity)
-            .insert(PreviousPosition::new(position.value()));
+            .insert((PreviousPosition::new(position.value()), PathLength::default()));
     }
 }
 
-pub fn run_every_n_frames(frames: Res<FrameCount>, config: Res<ExportOptions>) -> bool {
-    frames.0 != 0 && frames.0 % config.export_interval == 0
+pub fn run_every_n_ticks(time: Res<SimulationTime>, config: Res<ExportOptions>) -> bool {
+    time.ticks() != 0 && time.ticks() % config.export_interval == 0
 }
 
-fn write_data_and_clear_store(path: &str, store: &mut DataEntryStore) {
+// #######
+// Testing
+// #######
 
-    if store.len() == 0 {
-        return;
+#[cfg(test)]
+fn run_seeded_simulation(seed: u64, out: &std::path::Path) -> Vec<u8> {
+    use bevy::{app::PluginGroup, log::LogPlugin};
+
+    use crate::plugins::{
+        default::plugin::ECSMosHeadlessPlugins, movement_tracking::plugin::TrackingPlugin,
+        scenario_loader::plugin::ScenarioLoaderPlugin, start_time::plugin::StartTimePluging,
+    };
+    use crate::scenarios::built_in::BuiltInScenario;
+
+    let mut scenario = BuiltInScenario::NarrowOpening.load().unwrap();
+    scenario.seed = Some(seed);
+
+    let mut app = App::new();
+
+    app.add_plugins(ECSMosHeadlessPlugins.build().disable::<LogPlugin>())
+        .add_plugins(ScenarioLoaderPlugin { scenario })
+        .add_plugins(TrackingPlugin {
+            export_interval: 16,
+            out: out.to_string_lossy().to_string(),
+            ..Default::default()
+        })
+        .add_plugins(StartTimePluging);
+
+    for _ in 0..64 {
+        app.update();
+    }
+
+    app.world_mut().send_event(AppExit::Success);
+    app.update();
+
+    let data = std::fs::read(out).unwrap();
+    std::fs::remove_file(out).ok();
+
+    data
+}
+
+#[test]
+fn check_same_seed_produces_identical_output() {
+
+    // Setup
+
+    let directory = std::env::temp_dir().join(format!("ecsmos-seed-{}", std::process::id()));
+
+    // Act
+
+    let first = run_seeded_simulation(42, &directory.join("first.csv"));
+    let second = run_seeded_simulation(42, &directory.join("second.csv"));
+    let other = run_seeded_simulation(7, &directory.join("other.csv"));
+
+    std::fs::remove_dir(&directory).ok();
+
+    // Assert
+
+    assert!(!first.is_empty());
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+}
+
+#[test]
+fn check_path_length_accumulates() {
+
+    // Setup
+
+    let mut app = App::new();
+
+    app.add_systems(Update, (accumulate_path_length, record_previous_speed).chain());
+
+    let agent = app
+        .world_mut()
+        .spawn((Agent, Position::from(Vec2::ZERO), PreviousPosition::new(Vec2::ZERO), PathLength::default()))
+        .id();
+
+    // Act
+
+    app.update();
+
+    for position in [Vec2::new(3., 0.), Vec2::new(3., 4.)] {
+        app.world_mut().get_mut::<Position>(agent).unwrap().set_value(position);
+        app.update();
+    }
+
+    // Assert
+
+    assert_eq!(app.world().get::<PathLength>(agent).unwrap().0, 7.);
+}
+
+#[test]
+fn check_path_length_ignores_movement_undone_by_the_simulation_area() {
+    use bevy::math::Rect;
+
+    use crate::{
+        plugins::{
+            kinematics::plugin::KinematicsPlugin, movement_tracking::plugin::TrackingPlugin,
+            simulation_area::plugin::SimulationAreaPlugin, simulation_clock::plugin::SimulationClockPlugin,
+            start_time::plugin::StartTimePluging,
+        },
+        resources::configuration::SimulationMode,
+    };
+
+    // Setup
+
+    let directory = std::env::temp_dir().join(format!("ecsmos-path-length-{}", std::process::id()));
+
+    let mut app = App::new();
+
+    app.add_plugins(SimulationClockPlugin { mode: SimulationMode::AsFastAsPossible })
+        .add_plugins(KinematicsPlugin)
+        .add_plugins(SimulationAreaPlugin { simulation_area: Rect::from_center_size(Vec2::ZERO, Vec2::splat(10.)) })
+        .add_plugins(TrackingPlugin {
+            out: directory.join("trajectories.csv").to_string_lossy().to_string(),
+            ..Default::default()
+        })
+        .add_plugins(StartTimePluging)
+        .insert_resource(SimulationConfiguration { simulation_time_step: 1. });
+
+    // Walks into the wall at x = 5, where its center is stopped at 4.7
+    let agent = app.world_mut()
+        .spawn((Agent, Position::from(Vec2::new(4.5, 0.)), Speed::new(Vec2::new(1., 0.)), Shape::Circle(0.3)))
+        .id();
+
+    // Act
+
+    for _ in 0..5 {
+        app.update();
+    }
+
+    // Assert
+
+    let path_length = app.world().get::<PathLength>(agent).unwrap().0;
+
+    std::fs::remove_dir_all(&directory).ok();
+
+    assert!((path_length - 0.2).abs() < 1e-5);
+}
+
+#[cfg(test)]
+struct FailingExporter {
+    failures_left: u32,
+    written: std::sync::Arc<std::sync::Mutex<Vec<u32>>>,
+}
+
+#[cfg(test)]
+impl super::exporters::TrajectoryExporter for FailingExporter {
+    fn write(&mut self, _path: &std::path::Path, entries: &[DataEntry]) -> anyhow::Result<()> {
+        if self.failures_left > 0 {
+            self.failures_left -= 1;
+            anyhow::bail!("Disk full");
+        }
+
+        self.written.lock().unwrap().extend(entries.iter().map(|entry| entry.tick));
+        Ok(())
+    }
+}
+
+/// Runs `export_data` once per tick with one new entry each time, returns the ticks written, the events and whether the app exited
+#[cfg(test)]
+fn run_failing_export(policy: ExportErrorPolicy, failures: u32, ticks: u32) -> (Vec<u32>, Vec<ExportFailed>, bool) {
+    use std::sync::{Arc, Mutex};
+
+    let written = Arc::new(Mutex::new(Vec::new()));
+
+    let mut app = App::new();
+
+    app.add_event::<ExportFailed>()
+        .add_event::<AppExit>()
+        .insert_resource(StartTime(chrono::Utc::now()))
+        .insert_resource(ExportOptions {
+            export_interval: 1,
+            out: "unused.csv".to_string(),
+            format: super::configuration::ExportFormat::Csv,
+            error_policy: policy,
+        })
+        .insert_resource(TrajectoryWriter::spawn(
+            Box::new(FailingExporter { failures_left: failures, written: written.clone() }),
+            policy.retries(),
+        ))
+        .insert_resource(DataEntryStore::new())
+        .add_systems(Update, (collect_export_failures, export_data).chain());
+
+    let mut events = Vec::new();
+    let mut exited = false;
+
+    // The last update only collects the failures of the previous one
+    for tick in 0..=ticks {
+        if tick < ticks {
+            app.world_mut().resource_mut::<DataEntryStore>().add(super::exporters::test_entry(tick));
+        }
+
+        app.update();
+        app.world().resource::<TrajectoryWriter>().sync();
+
+        events.extend(app.world().resource::<Events<ExportFailed>>().iter_current_update_events().cloned());
+        exited |= app.world().resource::<Events<AppExit>>().iter_current_update_events().count() > 0;
     }
 
-    let path = Path::new(&path);
+    let written = written.lock().unwrap().clone();
+
+    (written, events, exited)
+}
+
+#[test]
+fn check_retry_recovers_from_transient_errors() {
+
+    // Act
+
+    let (written, events, exited) = run_failing_export(ExportErrorPolicy::Retry { attempts: 2 }, 2, 3);
+
+    // Assert
+
+    assert_eq!(written, [0, 1, 2]);
+    assert!(events.is_empty());
+    assert!(!exited);
+}
+
+#[test]
+fn check_buffer_keeps_entries_until_the_next_export() {
+
+    // Act
+
+    let (written, events, exited) = run_failing_export(ExportErrorPolicy::Buffer { max_entries: 10 }, 2, 3);
+
+    // Assert
+
+    // The failed entries are written before the later ones
+    assert_eq!(written, [0, 1, 2]);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].entries, 2);
+    assert!(!events[1].aborted);
+    assert!(!exited);
+}
 
-    if let Some(parent) = path.parent() {
-        create_dir_all(parent).unwrap();
+#[test]
+fn check_abort_ends_the_simulation() {
+
+    // Act
+
+    let (written, events, exited) = run_failing_export(ExportErrorPolicy::Abort, 1, 1);
+
+    // Assert
+
+    assert!(written.is_empty());
+    assert_eq!(events.len(), 1);
+    assert!(events[0].aborted);
+    assert_eq!(events[0].error, "Disk full");
+    assert!(exited);
+}
+
+#[cfg(test)]
+struct SlowExporter;
+
+#[cfg(test)]
+impl super::exporters::TrajectoryExporter for SlowExporter {
+    fn write(&mut self, _path: &std::path::Path, _entries: &[DataEntry]) -> anyhow::Result<()> {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        Ok(())
     }
+}
+
+#[test]
+fn check_busy_writer_keeps_the_store_within_the_buffer() {
 
-    let mut file = OpenOptions::new()
-        .write(true)
-        .append(true)
-        .create(true)
-        .open(path)
-        .unwrap();
+    // Setup
+
+    let max_entries = 2;
+
+    let mut app = App::new();
+
+    app.insert_resource(StartTime(chrono::Utc::now()))
+        .insert_resource(ExportOptions {
+            export_interval: 1,
+            out: "unused.csv".to_string(),
+            format: super::configuration::ExportFormat::Csv,
+            error_policy: ExportErrorPolicy::Buffer { max_entries },
+        })
+        .insert_resource(TrajectoryWriter::spawn(Box::new(SlowExporter), 0))
+        .insert_resource(DataEntryStore::new())
+        .add_systems(Update, export_data);
+
+    // Act
+
+    let mut largest_store = 0;
+
+    for tick in 0..20 {
+        app.world_mut().resource_mut::<DataEntryStore>().add(super::exporters::test_entry(tick));
+        app.update();
+
+        largest_store = largest_store.max(app.world().resource::<DataEntryStore>().len());
+    }
 
-    let data = format!("{}", store);
-    file.write_all(data.as_bytes()).unwrap();
+    // Assert
 
-    store.clear();
+    assert!(largest_store <= max_entries);
 }
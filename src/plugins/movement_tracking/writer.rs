@@ -0,0 +1,209 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, sync_channel, Receiver, SendError, Sender, SyncSender, TrySendError};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+use bevy::ecs::resource::Resource;
+use bevy::log::warn;
+
+use super::exporters::TrajectoryExporter;
+use super::resources::DataEntry;
+
+/// Number of exports that can wait for the writer thread before the entries are kept in the store
+pub const WRITER_CHANNEL_CAPACITY: usize = 4;
+
+enum WriterCommand {
+    Write { path: PathBuf, entries: Vec<DataEntry> },
+    Finish { path: PathBuf },
+    Sync(SyncSender<()>),
+}
+
+/// Failures reported back by the writer thread
+#[derive(Debug)]
+pub enum WriterFailure {
+    /// The writer keeps the `pending` entries and writes them before the next ones, so the file stays in tick order
+    Write { path: PathBuf, error: anyhow::Error, pending: usize },
+    Finish { path: PathBuf, error: anyhow::Error },
+}
+
+/// Runs the exporter on its own thread so writing never blocks the simulation
+#[derive(Resource)]
+pub struct TrajectoryWriter {
+    commands: Option<SyncSender<WriterCommand>>,
+    failures: Mutex<Receiver<WriterFailure>>,
+    thread: Option<JoinHandle<()>>,
+
+    /// Set once the simulation is aborted because of a failure, only the last export on exit is attempted
+    pub aborted: bool,
+}
+
+impl TrajectoryWriter {
+    /// `retries` is the number of times a failed write is attempted again right away
+    pub fn spawn(exporter: Box<dyn TrajectoryExporter>, retries: u32) -> Self {
+        let (commands, command_receiver) = sync_channel(WRITER_CHANNEL_CAPACITY);
+        let (failure_sender, failures) = channel();
+
+        let thread = thread::Builder::new()
+            .name("trajectory-writer".to_string())
+            .spawn(move || run_writer(exporter, retries, command_receiver, failure_sender))
+            .expect("Failed to spawn the trajectory writer thread");
+
+        Self {
+            commands: Some(commands),
+            failures: Mutex::new(failures),
+            thread: Some(thread),
+            aborted: false,
+        }
+    }
+
+    /// Queues the entries without waiting, they are handed back when the queue is full or the writer is closed
+    pub fn try_write(&self, path: PathBuf, entries: Vec<DataEntry>) -> Result<(), Vec<DataEntry>> {
+        let Some(commands) = &self.commands else {
+            return Err(entries);
+        };
+
+        match commands.try_send(WriterCommand::Write { path, entries }) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(WriterCommand::Write { entries, .. }))
+            | Err(TrySendError::Disconnected(WriterCommand::Write { entries, .. })) => Err(entries),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Queues the entries, waiting for the writer when the queue is full, they are handed back when the writer is closed
+    pub fn write(&self, path: PathBuf, entries: Vec<DataEntry>) -> Result<(), Vec<DataEntry>> {
+        let Some(commands) = &self.commands else {
+            return Err(entries);
+        };
+
+        match commands.send(WriterCommand::Write { path, entries }) {
+            Ok(()) => Ok(()),
+            Err(SendError(WriterCommand::Write { entries, .. })) => Err(entries),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Failures reported since the last call
+    pub fn failures(&mut self) -> Vec<WriterFailure> {
+        let failures = self.failures.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner());
+        failures.try_iter().collect()
+    }
+
+    /// Blocks until every command sent so far is processed
+    pub fn sync(&self) {
+        let Some(commands) = &self.commands else {
+            return;
+        };
+
+        let (done, wait) = sync_channel(1);
+        if commands.send(WriterCommand::Sync(done)).is_ok() {
+            wait.recv().ok();
+        }
+    }
+
+    /// Writes the last entries after the pending ones, finishes the file and waits for the thread, returns every failure not reported yet
+    pub fn close(&mut self, path: &Path, entries: Vec<DataEntry>) -> Vec<WriterFailure> {
+        if let Some(commands) = self.commands.take() {
+            if !entries.is_empty() {
+                commands.send(WriterCommand::Write { path: path.to_path_buf(), entries }).ok();
+            }
+
+            commands.send(WriterCommand::Finish { path: path.to_path_buf() }).ok();
+        }
+
+        self.join();
+        self.failures()
+    }
+
+    fn join(&mut self) {
+        // Dropping the sender ends the loop of the thread once the queue is empty
+        self.commands = None;
+
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                warn!("The trajectory writer thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for TrajectoryWriter {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+fn run_writer(
+    mut exporter: Box<dyn TrajectoryExporter>,
+    retries: u32,
+    commands: Receiver<WriterCommand>,
+    failures: Sender<WriterFailure>,
+) {
+    // Entries of the failed writes, the later ones are only written after them
+    let mut pending = Vec::new();
+
+    for command in commands {
+        match command {
+            WriterCommand::Write { path, entries } => {
+                pending.extend(entries);
+
+                if let Some(failure) = write_pending(exporter.as_mut(), path, &mut pending, retries) {
+                    failures.send(failure).ok();
+                }
+            }
+            WriterCommand::Finish { path } => {
+                // Entries that still cannot be written are lost, the file is finished with the ones written so far
+                if !pending.is_empty() {
+                    if let Some(failure) = write_pending(exporter.as_mut(), path.clone(), &mut pending, retries) {
+                        failures.send(failure).ok();
+                    }
+
+                    pending.clear();
+                }
+
+                if let Err(error) = exporter.finish(&path) {
+                    failures.send(WriterFailure::Finish { path, error }).ok();
+                }
+            }
+            WriterCommand::Sync(done) => {
+                done.send(()).ok();
+            }
+        }
+    }
+}
+
+/// Writes the pending entries, they are kept when the write fails
+fn write_pending(
+    exporter: &mut dyn TrajectoryExporter,
+    path: PathBuf,
+    pending: &mut Vec<DataEntry>,
+    retries: u32,
+) -> Option<WriterFailure> {
+    match write_with_retries(exporter, &path, pending, retries) {
+        Ok(()) => {
+            pending.clear();
+            None
+        }
+        Err(error) => Some(WriterFailure::Write { path, error, pending: pending.len() }),
+    }
+}
+
+fn write_with_retries(
+    exporter: &mut dyn TrajectoryExporter,
+    path: &Path,
+    entries: &[DataEntry],
+    retries: u32,
+) -> anyhow::Result<()> {
+    let mut result = exporter.write(path, entries);
+
+    for attempt in 1..=retries {
+        let Err(error) = &result else {
+            break;
+        };
+
+        warn!("{:#}, retrying ({}/{})", error, attempt, retries);
+        result = exporter.write(path, entries);
+    }
+
+    result
+}
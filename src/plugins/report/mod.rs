@@ -0,0 +1,4 @@
+pub mod models;
+pub mod plugin;
+pub mod resources;
+pub mod systems;
\ No newline at end of file
@@ -0,0 +1,158 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use serde::Serialize;
+
+use crate::plugins::simple_objective::resources::ArrivalStatistics;
+
+pub const TRAVEL_TIME_PERCENTILES: [f32; 5] = [5., 25., 50., 75., 95.];
+
+/// Summary of a run, written when the simulation ends
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SimulationReport {
+    /// Wall-clock time the run started at
+    pub start_time: String,
+
+    /// (s)
+    pub simulated_time: f32,
+    pub ticks: u32,
+
+    pub agents_spawned: u32,
+
+    /// Agents that reached their final objective
+    pub agents_arrived: u32,
+
+    /// Objectives completed by the agents, intermediate waypoints included
+    pub arrivals_per_objective: BTreeMap<String, u32>,
+
+    /// Time the last agent left the simulation at (s)
+    pub last_exit_time: Option<f32>,
+
+    pub travel_time_percentiles: Vec<TravelTimePercentile>,
+    pub flow_rate: Vec<FlowRateWindow>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct TravelTimePercentile {
+    pub percentile: f32,
+
+    /// (s)
+    pub travel_time: f32,
+}
+
+/// Agents leaving the simulation during `[start, end)`
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct FlowRateWindow {
+    /// (s)
+    pub start: f32,
+    /// (s)
+    pub end: f32,
+    pub exits: u32,
+
+    /// (1/s)
+    pub rate: f32,
+}
+
+impl SimulationReport {
+    /// `objective_name` gives the label of each objective in `arrivals_per_objective`
+    pub fn new(
+        start_time: String,
+        simulated_time: f32,
+        ticks: u32,
+        agents_spawned: u32,
+        statistics: &ArrivalStatistics,
+        objective_name: impl Fn(bevy::ecs::entity::Entity) -> String,
+        flow_interval: f32,
+    ) -> Self {
+        let mut arrivals_per_objective = BTreeMap::new();
+
+        for arrival in statistics.arrivals() {
+            *arrivals_per_objective.entry(objective_name(arrival.objective)).or_default() += 1;
+        }
+
+        let mut travel_times = statistics.evacuation_times();
+        travel_times.sort_by(f32::total_cmp);
+
+        let travel_time_percentiles = if travel_times.is_empty() {
+            Vec::new()
+        } else {
+            TRAVEL_TIME_PERCENTILES
+                .iter()
+                .map(|&percentile| TravelTimePercentile {
+                    percentile,
+                    travel_time: percentile_of_sorted(&travel_times, percentile),
+                })
+                .collect()
+        };
+
+        let exit_times: Vec<f32> = statistics.exits().map(|a| a.arrival_time).collect();
+
+        Self {
+            start_time,
+            simulated_time,
+            ticks,
+            agents_spawned,
+            agents_arrived: exit_times.len() as u32,
+            arrivals_per_objective,
+            last_exit_time: statistics.last_exit_time(),
+            travel_time_percentiles,
+            flow_rate: flow_rate_windows(&exit_times, simulated_time, flow_interval),
+        }
+    }
+
+    /// Long format with one `metric,key,value` row per value
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("metric,key,value\n");
+
+        let optional = |value: Option<f32>| value.map_or(String::new(), |v| v.to_string());
+
+        // Writing to a String can not fail
+        writeln!(csv, "start_time,,{}", self.start_time).unwrap();
+        writeln!(csv, "simulated_time,,{}", self.simulated_time).unwrap();
+        writeln!(csv, "ticks,,{}", self.ticks).unwrap();
+        writeln!(csv, "agents_spawned,,{}", self.agents_spawned).unwrap();
+        writeln!(csv, "agents_arrived,,{}", self.agents_arrived).unwrap();
+        writeln!(csv, "last_exit_time,,{}", optional(self.last_exit_time)).unwrap();
+
+        for (objective, arrivals) in &self.arrivals_per_objective {
+            writeln!(csv, "arrivals,{},{}", objective, arrivals).unwrap();
+        }
+
+        for percentile in &self.travel_time_percentiles {
+            writeln!(csv, "travel_time_percentile,{},{}", percentile.percentile, percentile.travel_time).unwrap();
+        }
+
+        for window in &self.flow_rate {
+            writeln!(csv, "flow_rate,{}-{},{}", window.start, window.end, window.rate).unwrap();
+        }
+
+        csv
+    }
+}
+
+/// Linear interpolation between the closest ranks, `sorted` must not be empty
+pub fn percentile_of_sorted(sorted: &[f32], percentile: f32) -> f32 {
+    let rank = (percentile / 100.).clamp(0., 1.) * (sorted.len() - 1) as f32;
+
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f32)
+}
+
+fn flow_rate_windows(exit_times: &[f32], simulated_time: f32, interval: f32) -> Vec<FlowRateWindow> {
+    if interval <= 0. || simulated_time <= 0. {
+        return Vec::new();
+    }
+
+    let count = (simulated_time / interval).ceil() as usize;
+
+    (0..count)
+        .map(|i| {
+            let start = i as f32 * interval;
+            let end = start + interval;
+            let exits = exit_times.iter().filter(|&&t| t >= start && t < end).count() as u32;
+
+            FlowRateWindow { start, end, exits, rate: exits as f32 / interval }
+        })
+        .collect()
+}
@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+
+use crate::plugins::simulation_clock::plugin::SimulationPostUpdate;
+
+use super::{
+    resources::{ReportFormat, ReportOptions, SpawnedAgents},
+    systems::*,
+};
+
+/// Writes a `SimulationReport` next to the tracking output when the simulation ends
+pub struct ReportPlugin {
+    pub format: ReportFormat,
+
+    /// Width of the windows the flow rate is measured over (s)
+    pub flow_interval: f32,
+}
+
+impl Default for ReportPlugin {
+    fn default() -> Self {
+        Self {
+            format: ReportFormat::Json,
+            flow_interval: 10.,
+        }
+    }
+}
+
+impl Plugin for ReportPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ReportOptions {
+            format: self.format,
+            flow_interval: self.flow_interval,
+        })
+        .init_resource::<SpawnedAgents>();
+
+        app.add_systems(SimulationPostUpdate, count_spawned_agents)
+            .add_systems(Last, write_report_on_close);
+    }
+}
@@ -0,0 +1,31 @@
+use bevy::ecs::resource::Resource;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl ReportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Json => "json",
+            ReportFormat::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Resource, Clone, Copy)]
+pub struct ReportOptions {
+    pub format: ReportFormat,
+
+    /// Width of the windows the flow rate is measured over (s)
+    pub flow_interval: f32,
+}
+
+/// Number of agents that entered the simulation
+#[derive(Resource, Default)]
+pub struct SpawnedAgents(pub u32);
@@ -0,0 +1,132 @@
+use std::{
+    fs::{create_dir_all, write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use bevy::prelude::*;
+
+use crate::{
+    plugins::{
+        movement_tracking::configuration::ExportOptions,
+        simple_objective::resources::ArrivalStatistics,
+        spawner::components::SpawnTime,
+        start_time::resources::StartTime,
+    },
+    resources::configuration::SimulationTime,
+};
+
+use super::{
+    models::SimulationReport,
+    resources::{ReportFormat, ReportOptions, SpawnedAgents},
+};
+
+pub fn count_spawned_agents(mut spawned: ResMut<SpawnedAgents>, agents: Query<(), Added<SpawnTime>>) {
+    spawned.0 += agents.iter().count() as u32;
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn write_report_on_close(
+    exit_events: EventReader<AppExit>,
+    start_time: Res<StartTime>,
+    export_options: Res<ExportOptions>,
+    options: Res<ReportOptions>,
+    time: Res<SimulationTime>,
+    spawned: Res<SpawnedAgents>,
+    statistics: Res<ArrivalStatistics>,
+    names: Query<&Name>,
+) {
+    if exit_events.is_empty() {
+        return;
+    }
+
+    let report = SimulationReport::new(
+        start_time.0.to_rfc3339(),
+        time.elapsed(),
+        time.ticks(),
+        spawned.0,
+        &statistics,
+        |objective| names.get(objective).map_or(objective.to_string(), |name| name.to_string()),
+        options.flow_interval,
+    );
+
+    let path = report_path(export_options.path(&start_time.0), options.format);
+
+    match write_report(&path, &report, options.format) {
+        Ok(()) => info!("Report written to {}", path.display()),
+        Err(error) => error!("{:#}", error),
+    }
+}
+
+/// The tracking output path with a `-report` suffix and the extension of the format
+pub fn report_path(tracking_out: impl AsRef<Path>, format: ReportFormat) -> PathBuf {
+    let path = tracking_out.as_ref();
+    let stem = path.file_stem().map_or("".into(), |s| s.to_string_lossy());
+
+    path.with_file_name(format!("{}-report.{}", stem, format.extension()))
+}
+
+fn write_report(path: &Path, report: &SimulationReport, format: ReportFormat) -> anyhow::Result<()> {
+    let content = match format {
+        ReportFormat::Json => serde_json::to_string_pretty(report)?,
+        ReportFormat::Csv => report.to_csv(),
+    };
+
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent).with_context(|| format!("Could not create directory {}", parent.display()))?;
+    }
+
+    write(path, content).with_context(|| format!("Could not write report {}", path.display()))
+}
+
+// #######
+// Testing
+// #######
+
+#[test]
+fn check_report_summarizes_arrivals() {
+    use crate::plugins::simple_objective::events::AgentArrived;
+
+    // Setup
+
+    let objective = Entity::from_raw(100);
+    let mut statistics = ArrivalStatistics::default();
+
+    for (i, (spawn_time, arrival_time)) in [(0., 4.), (0., 6.), (2., 12.), (5., 25.)].into_iter().enumerate() {
+        statistics.add(AgentArrived {
+            agent: Entity::from_raw(i as u32),
+            objective,
+            spawn_time,
+            arrival_time,
+            path_length: 10.,
+            mean_speed: 10. / (arrival_time - spawn_time),
+            final_objective: true,
+        });
+    }
+
+    // Act
+
+    let report = SimulationReport::new("start".into(), 30., 150, 5, &statistics, |_| "exit".into(), 10.);
+
+    // Assert
+
+    assert_eq!(report.agents_spawned, 5);
+    assert_eq!(report.agents_arrived, 4);
+    assert_eq!(report.arrivals_per_objective["exit"], 4);
+    assert_eq!(report.last_exit_time, Some(25.));
+
+    let median = report.travel_time_percentiles.iter().find(|p| p.percentile == 50.).unwrap();
+    assert_eq!(median.travel_time, 8.);
+
+    let exits: Vec<u32> = report.flow_rate.iter().map(|w| w.exits).collect();
+    assert_eq!(exits, vec![2, 1, 1]);
+    assert_eq!(report.flow_rate[0].rate, 0.2);
+
+    assert!(report.to_csv().contains("agents_arrived,,4\n"));
+}
+
+#[test]
+fn check_report_path_follows_tracking_output() {
+    assert_eq!(report_path("./out/2025.txt", ReportFormat::Json), PathBuf::from("./out/2025-report.json"));
+    assert_eq!(report_path("run", ReportFormat::Csv), PathBuf::from("run-report.csv"));
+}
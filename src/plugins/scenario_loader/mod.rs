@@ -0,0 +1,3 @@
+pub mod models;
+pub mod plugin;
+pub mod systems;
\ No newline at end of file
@@ -0,0 +1,330 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use anyhow::{bail, ensure, Context};
+use bevy::{ecs::resource::Resource, math::{Rect, Vec2}};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::prelude::Shape,
+    plugins::{
+        auto_end_simulation::resources::StopCondition,
+        flow_field_pathfinding::configuration::{DensityComputationStrategy, FlowFieldConstants, ProximityComputationStrategy},
+        simple_objective::configuration::ArrivalCriterion,
+        social_foces_model::configuration::SocialForcesModelConfiguration,
+        spawner::components::SpawnerAgentParameters,
+    },
+    resources::configuration::VoronoiCutoff,
+    utils::distribution::ParameterDistribution,
+};
+
+/// Full description of a simulation setup, loaded from a RON or JSON file
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    pub simulation_area: AreaDescription,
+
+    /// Number of seconds the simulation is advance by for each iteration (s)
+    pub simulation_time_step: f32,
+
+    /// Seed of the `SimulationRng`, a random one is picked when missing
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    #[serde(default)]
+    pub social_forces: SocialForcesModelConfiguration,
+
+    #[serde(default)]
+    pub flow_field: FlowFieldDescription,
+
+    /// Radius the Voronoi cells of the agents are cut off at, for the measurements and the Voronoi density fields (m)
+    #[serde(default = "default_voronoi_cutoff")]
+    pub voronoi_cutoff: f32,
+
+    /// How agents reach the objectives, by their center or by touching them
+    #[serde(default)]
+    pub arrival: ArrivalCriterion,
+
+    #[serde(default)]
+    pub objectives: Vec<ObjectiveDescription>,
+
+    #[serde(default)]
+    pub spawners: Vec<SpawnerDescription>,
+
+    #[serde(default)]
+    pub obstacles: Vec<ObstacleDescription>,
+
+    #[serde(default)]
+    pub agents: Vec<AgentDescription>,
+
+    /// The simulation ends when any of them is met, it ends once the spawners are done and no agents are left when empty
+    #[serde(default)]
+    pub stop_conditions: Vec<StopCondition>,
+
+    /// Areas the density, speed and flow are measured in
+    #[serde(default)]
+    pub measurement_areas: Vec<MeasurementAreaDescription>,
+
+    /// Lines the flow is measured across, every crossing agent is recorded by direction
+    #[serde(default)]
+    pub measurement_lines: Vec<MeasurementLineDescription>,
+}
+
+impl Scenario {
+    /// Loads a scenario, the format is picked from the file extension (`.ron` or `.json`)
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Could not read scenario file {}", path.display()))?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("ron") => Self::from_ron(&content),
+            Some("json") => Self::from_json(&content),
+            _ => bail!("Unsupported scenario format, expected .ron or .json"),
+        }
+        .with_context(|| format!("Invalid scenario file {}", path.display()))
+    }
+
+    pub fn from_ron(content: &str) -> anyhow::Result<Self> {
+        let scenario: Scenario = ron::from_str(content).context("Could not parse RON scenario")?;
+        scenario.validate()?;
+
+        Ok(scenario)
+    }
+
+    pub fn from_json(content: &str) -> anyhow::Result<Self> {
+        let scenario: Scenario = serde_json::from_str(content).context("Could not parse JSON scenario")?;
+        scenario.validate()?;
+
+        Ok(scenario)
+    }
+
+    /// Checks that the scenario is consistent, e.g. that every destination names an existing objective
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(self.simulation_time_step > 0., "Simulation time step must be positive");
+        ensure!(self.flow_field.cell_size > 0., "Flow field cell size must be positive");
+        ensure!(self.voronoi_cutoff > 0., "Voronoi cut off must be positive");
+        ensure!(self.flow_field.constants.recompute_interval > 0, "Flow field recompute interval must be positive");
+
+        let mut names = HashSet::new();
+
+        for objective in &self.objectives {
+            ensure!(names.insert(objective.name.as_str()), "Duplicated objective name {}", objective.name);
+        }
+
+        let destinations = self.spawners.iter().flat_map(|s| std::iter::once(&s.destination).chain(&s.itinerary))
+            .chain(self.agents.iter().flat_map(|a| std::iter::once(&a.destination).chain(&a.itinerary)));
+
+        for destination in destinations {
+            ensure!(names.contains(destination.as_str()), "Unknown objective {}", destination);
+        }
+
+        for (i, spawner) in self.spawners.iter().enumerate() {
+            if let Some(parameters) = &spawner.agent_parameters {
+                let distributions = [("radius", parameters.radius), ("desired speed", parameters.desired_speed), ("mass", parameters.mass)];
+
+                for (name, distribution) in distributions {
+                    if let Some(distribution) = distribution {
+                        distribution.validate().with_context(|| format!("Invalid {} of spawner {}", name, i))?;
+                    }
+                }
+            }
+        }
+
+        for objective in &self.objectives {
+            if let Some(service) = &objective.service {
+                service.time.validate().with_context(|| format!("Invalid service time of objective {}", objective.name))?;
+            }
+        }
+
+        for (i, obstacle) in self.obstacles.iter().enumerate() {
+            if let ShapeDescription::Polygon(points) = &obstacle.shape {
+                ensure!(points.len() >= 3, "Obstacle {} has less than 3 points", i);
+            }
+        }
+
+        for area in &self.measurement_areas {
+            match &area.shape {
+                MeasurementShapeDescription::Rectangle(size) => {
+                    ensure!(size[0] > 0. && size[1] > 0., "Measurement area {} has an empty rectangle", area.name)
+                }
+                MeasurementShapeDescription::Polygon(points) => {
+                    ensure!(points.len() >= 3, "Measurement area {} has less than 3 points", area.name)
+                }
+            }
+        }
+
+        for line in &self.measurement_lines {
+            ensure!(line.start != line.end, "Line {} has no length", line.name);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct AreaDescription {
+    pub center: [f32; 2],
+    pub size: [f32; 2],
+}
+
+impl From<AreaDescription> for Rect {
+    fn from(value: AreaDescription) -> Self {
+        Rect::from_center_size(Vec2::from(value.center), Vec2::from(value.size))
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlowFieldDescription {
+    /// Length of the side of each cell of the grid (m)
+    pub cell_size: f32,
+    pub constants: FlowFieldConstants,
+
+    /// How the agent density the routing avoids is computed
+    pub density: DensityComputationStrategy,
+
+    /// How the distances to the objectives are computed
+    pub proximity: ProximityComputationStrategy,
+}
+
+impl Default for FlowFieldDescription {
+    fn default() -> Self {
+        Self { cell_size: 0.3, constants: FlowFieldConstants::default(), density: DensityComputationStrategy::default(), proximity: ProximityComputationStrategy::default() }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ShapeDescription {
+    Circle(f32),
+
+    // Points have to be counterclockwise
+    Polygon(Vec<[f32; 2]>),
+}
+
+impl From<&ShapeDescription> for Shape {
+    fn from(value: &ShapeDescription) -> Self {
+        match value {
+            ShapeDescription::Circle(radius) => Shape::Circle(*radius),
+            ShapeDescription::Polygon(points) => Shape::Polygon(points.iter().copied().map(Vec2::from).collect()),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ObjectiveDescription {
+    /// Used by spawners and agents to reference the objective
+    pub name: String,
+    pub ordering: u32,
+    pub position: [f32; 2],
+    pub shape: ShapeDescription,
+
+    /// Agents wait at the objective before moving on when present
+    #[serde(default)]
+    pub service: Option<ServiceDescription>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct ServiceDescription {
+    /// (s)
+    pub time: ParameterDistribution,
+
+    /// Agents served at the same time, unlimited when missing
+    #[serde(default)]
+    pub capacity: Option<u32>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SpawnerDescription {
+    pub position: [f32; 2],
+
+    /// Half extents of the rectangle agents are spawned in (m)
+    pub area: [f32; 2],
+    pub schedule: SpawnerScheduleDescription,
+    pub destination: String,
+
+    /// Objectives visited after `destination`, in order
+    #[serde(default)]
+    pub itinerary: Vec<String>,
+
+    /// Distributions of the spawned agents parameters, the model defaults are used for the missing ones
+    #[serde(default)]
+    pub agent_parameters: Option<SpawnerAgentParameters>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct SpawnerScheduleDescription {
+    pub start_time: f32,
+    pub end_time: f32,
+    pub interval: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ObstacleDescription {
+    pub position: [f32; 2],
+    pub shape: ShapeDescription,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AgentDescription {
+    pub position: [f32; 2],
+
+    #[serde(default = "default_agent_radius")]
+    pub radius: f32,
+
+    #[serde(default)]
+    pub speed: [f32; 2],
+    pub destination: String,
+
+    /// Objectives visited after `destination`, in order
+    #[serde(default)]
+    pub itinerary: Vec<String>,
+
+    /// (m/s), the model default is used when missing
+    #[serde(default)]
+    pub desired_speed: Option<f32>,
+
+    /// (Kg), the model default is used when missing
+    #[serde(default)]
+    pub mass: Option<f32>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum MeasurementShapeDescription {
+    /// Width and height, centered on the position (m)
+    Rectangle([f32; 2]),
+
+    // Points have to be counterclockwise
+    Polygon(Vec<[f32; 2]>),
+}
+
+impl From<&MeasurementShapeDescription> for Shape {
+    fn from(value: &MeasurementShapeDescription) -> Self {
+        match value {
+            MeasurementShapeDescription::Rectangle([width, height]) => {
+                let half = Vec2::new(width / 2., height / 2.);
+                Shape::Polygon(vec![-half, Vec2::new(half.x, -half.y), half, Vec2::new(-half.x, half.y)])
+            }
+            MeasurementShapeDescription::Polygon(points) => Shape::Polygon(points.iter().copied().map(Vec2::from).collect()),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MeasurementAreaDescription {
+    pub name: String,
+    pub position: [f32; 2],
+    pub shape: MeasurementShapeDescription,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MeasurementLineDescription {
+    pub name: String,
+    pub start: [f32; 2],
+    pub end: [f32; 2],
+}
+
+fn default_agent_radius() -> f32 {
+    0.3
+}
+
+fn default_voronoi_cutoff() -> f32 {
+    VoronoiCutoff::default().0
+}
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+
+use crate::{
+    plugins::{
+        flow_field_pathfinding::plugin::FlowFieldPathfindingPlugin,
+        kinematics::plugin::KinematicsPlugin,
+        simple_objective::{configuration::ObjectiveConfiguration, plugin::SimpleObjective},
+        simulation_area::plugin::SimulationAreaPlugin,
+        social_foces_model::plugin::SocialForcesPlugin,
+        spawner::plugin::SpawnerPlugin,
+    },
+    resources::{
+        configuration::{SimulationConfiguration, VoronoiCutoff},
+        rng::SimulationRng,
+    },
+};
+
+use super::{models::Scenario, systems::spawn_scenario_entities};
+
+/// Adds the simulation plugins configured by a `Scenario` and spawns its entities on startup
+pub struct ScenarioLoaderPlugin {
+    pub scenario: Scenario,
+}
+
+impl Plugin for ScenarioLoaderPlugin {
+    fn build(&self, app: &mut App) {
+        let scenario = &self.scenario;
+
+        // Before the plugins, the flow field sizes its obstacle grid by it
+        app.insert_resource(VoronoiCutoff(scenario.voronoi_cutoff));
+
+        // The spawner and objective plugins can run without a scenario, they may already be added
+        if !app.is_plugin_added::<SimpleObjective>() {
+            app.add_plugins(SimpleObjective {
+                configuration: ObjectiveConfiguration {
+                    arrival: scenario.arrival,
+                },
+            });
+        }
+
+        if !app.is_plugin_added::<SpawnerPlugin>() {
+            app.add_plugins(SpawnerPlugin);
+        }
+
+        app.add_plugins(KinematicsPlugin)
+            .add_plugins(SimulationAreaPlugin {
+                simulation_area: scenario.simulation_area.into(),
+            })
+            .add_plugins(SocialForcesPlugin {
+                configuration: scenario.social_forces,
+            })
+            .add_plugins(FlowFieldPathfindingPlugin {
+                cell_size: scenario.flow_field.cell_size,
+                constants: scenario.flow_field.constants,
+                density: scenario.flow_field.density,
+                proximity: scenario.flow_field.proximity,
+            });
+
+        let seed = scenario.seed.unwrap_or_else(rand::random);
+        info!("Using seed {}", seed);
+
+        app.insert_resource(SimulationConfiguration {
+            simulation_time_step: scenario.simulation_time_step,
+        })
+        .insert_resource(SimulationRng::new(seed))
+        .insert_resource(scenario.clone())
+        .add_systems(Startup, spawn_scenario_entities);
+    }
+}
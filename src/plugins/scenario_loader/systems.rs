@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    components::prelude::*,
+    plugins::{
+        flow_field_pathfinding::components::Ordering,
+        measurement::components::{MeasurementArea, MeasurementLine},
+        simple_objective::components::{ServiceCapacity, ServiceTime},
+        social_foces_model::components::{DesiredSpeed, Mass},
+        spawner::components::{Spawner, SpawnerArea, SpawnerDestination, SpawnerItinerary, SpawnerSchedule},
+    },
+};
+
+use super::models::Scenario;
+
+pub fn spawn_scenario_entities(mut commands: Commands, scenario: Res<Scenario>) {
+
+    let mut objectives = HashMap::new();
+
+    for objective in &scenario.objectives {
+        let mut entity = commands.spawn((
+            Objective,
+            Name::new(objective.name.clone()),
+            Ordering(objective.ordering),
+            Shape::from(&objective.shape),
+            Position::from(Vec2::from(objective.position)),
+        ));
+
+        if let Some(service) = objective.service {
+            entity.insert(ServiceTime(service.time));
+
+            if let Some(capacity) = service.capacity {
+                entity.insert(ServiceCapacity(capacity));
+            }
+        }
+
+        objectives.insert(objective.name.as_str(), entity.id());
+    }
+
+    for obstacle in &scenario.obstacles {
+        commands.spawn((
+            Obstacle,
+            Shape::from(&obstacle.shape),
+            Position::from(Vec2::from(obstacle.position)),
+        ));
+    }
+
+    for area in &scenario.measurement_areas {
+        commands.spawn((
+            MeasurementArea,
+            Name::new(area.name.clone()),
+            Shape::from(&area.shape),
+            Position::from(Vec2::from(area.position)),
+        ));
+    }
+
+    for line in &scenario.measurement_lines {
+        commands.spawn((
+            Name::new(line.name.clone()),
+            MeasurementLine {
+                start: Vec2::from(line.start),
+                end: Vec2::from(line.end),
+            },
+        ));
+    }
+
+    for spawner in &scenario.spawners {
+        let mut entity = commands.spawn((
+            Spawner,
+            Position::from(Vec2::from(spawner.position)),
+            SpawnerArea(Vec2::from(spawner.area)),
+            SpawnerSchedule {
+                start_time: spawner.schedule.start_time,
+                end_time: spawner.schedule.end_time,
+                interval: spawner.schedule.interval,
+                last_spawn: 0.,
+            },
+            SpawnerDestination(objectives[spawner.destination.as_str()]),
+        ));
+
+        if let Some(parameters) = spawner.agent_parameters {
+            entity.insert(parameters);
+        }
+
+        if !spawner.itinerary.is_empty() {
+            entity.insert(SpawnerItinerary(itinerary(&objectives, &spawner.destination, &spawner.itinerary)));
+        }
+    }
+
+    for agent in &scenario.agents {
+        let mut entity = commands.spawn((
+            Agent,
+            Shape::Circle(agent.radius),
+            Speed::new(Vec2::from(agent.speed)),
+            Position::from(Vec2::from(agent.position)),
+            Destination(objectives[agent.destination.as_str()]),
+        ));
+
+        if let Some(desired_speed) = agent.desired_speed {
+            entity.insert(DesiredSpeed(desired_speed));
+        }
+
+        if let Some(mass) = agent.mass {
+            entity.insert(Mass(mass));
+        }
+
+        if !agent.itinerary.is_empty() {
+            entity.insert(Itinerary::new(itinerary(&objectives, &agent.destination, &agent.itinerary)));
+        }
+    }
+}
+
+fn itinerary(objectives: &HashMap<&str, Entity>, destination: &str, waypoints: &[String]) -> Vec<Entity> {
+    std::iter::once(destination)
+        .chain(waypoints.iter().map(String::as_str))
+        .map(|name| objectives[name])
+        .collect()
+}
+
+// #######
+// Testing
+// #######
+
+#[test]
+fn check_scenario_files_load() {
+    use std::path::Path;
+
+    for file in ["scenarios/narrow_opening.ron", "scenarios/corridor.ron"] {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(file);
+
+        assert!(Scenario::from_file(&path).is_ok(), "Could not load {}", file);
+    }
+}
+
+#[test]
+fn check_scenario_entities_are_spawned() {
+
+    // Setup
+
+    let scenario: Scenario = ron::from_str(r#"(
+        simulation_area: (center: (0., 0.), size: (10., 10.)),
+        simulation_time_step: 0.1,
+        objectives: [(name: "exit", ordering: 0, position: (4., 0.), shape: Circle(1.))],
+        obstacles: [(position: (0., 0.), shape: Polygon([(0., 0.), (1., 0.), (1., 1.)]))],
+        agents: [
+            (position: (-4., 0.), destination: "exit"),
+            (position: (-4., 1.), radius: 0.2, destination: "exit"),
+        ],
+    )"#).unwrap();
+
+    scenario.validate().unwrap();
+
+    let mut app = App::new();
+
+    app.insert_resource(scenario);
+    app.add_systems(Update, spawn_scenario_entities);
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    let world = app.world_mut();
+
+    let objective = world.query_filtered::<Entity, With<Objective>>().single(world).unwrap();
+
+    assert_eq!(world.query::<&Obstacle>().iter(world).len(), 1);
+    assert!(world.query::<&Destination>().iter(world).all(|d| d.0 == objective));
+    assert_eq!(world.query::<&Agent>().iter(world).len(), 2);
+}
+
+#[test]
+fn check_unknown_destination_is_rejected() {
+    let scenario: Scenario = ron::from_str(r#"(
+        simulation_area: (center: (0., 0.), size: (10., 10.)),
+        simulation_time_step: 0.1,
+        agents: [(position: (0., 0.), destination: "missing")],
+    )"#).unwrap();
+
+    assert!(scenario.validate().is_err());
+}
+
+#[test]
+fn check_agent_itinerary_is_spawned() {
+
+    // Setup
+
+    let scenario: Scenario = ron::from_str(r#"(
+        simulation_area: (center: (0., 0.), size: (10., 10.)),
+        simulation_time_step: 0.1,
+        objectives: [
+            (name: "gate", ordering: 0, position: (0., 0.), shape: Circle(1.)),
+            (name: "platform", ordering: 1, position: (4., 0.), shape: Circle(1.)),
+        ],
+        agents: [(position: (-4., 0.), destination: "gate", itinerary: ["platform"])],
+    )"#).unwrap();
+
+    scenario.validate().unwrap();
+
+    let mut app = App::new();
+
+    app.insert_resource(scenario);
+    app.add_systems(Update, spawn_scenario_entities);
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    let world = app.world_mut();
+
+    let (destination, itinerary) = world.query::<(&Destination, &Itinerary)>().single(world).unwrap();
+
+    assert_eq!(itinerary.waypoints().len(), 2);
+    assert_eq!(itinerary.current(), Some(destination.0));
+}
+
+#[test]
+fn check_unknown_itinerary_waypoint_is_rejected() {
+    let scenario: Scenario = ron::from_str(r#"(
+        simulation_area: (center: (0., 0.), size: (10., 10.)),
+        simulation_time_step: 0.1,
+        objectives: [(name: "gate", ordering: 0, position: (0., 0.), shape: Circle(1.))],
+        agents: [(position: (0., 0.), destination: "gate", itinerary: ["missing"])],
+    )"#).unwrap();
+
+    assert!(scenario.validate().is_err());
+}
+
+#[test]
+fn check_invalid_distributions_are_rejected() {
+    let scenario_with = |agent_parameters: &str, service: &str| -> Scenario {
+        ron::from_str(&format!(r#"(
+            simulation_area: (center: (0., 0.), size: (10., 10.)),
+            simulation_time_step: 0.1,
+            objectives: [(name: "gate", ordering: 0, position: (0., 0.), shape: Circle(1.), service: {})],
+            spawners: [(
+                position: (-4., 0.),
+                area: (1., 1.),
+                schedule: (start_time: 0., end_time: 10., interval: 1.),
+                destination: "gate",
+                agent_parameters: {},
+            )],
+        )"#, service, agent_parameters)).unwrap()
+    };
+
+    let valid = scenario_with("Some((desired_speed: Some(Uniform(min: 1., max: 1.5))))", "Some((time: Constant(2.)))");
+    let negative_std_dev = scenario_with("Some((desired_speed: Some(Normal(mean: 1.34, std_dev: -0.26, min: 0.5, max: 2.))))", "None");
+    let inverted_bounds = scenario_with("Some((radius: Some(Uniform(min: 0.3, max: 0.2))))", "None");
+    let invalid_service = scenario_with("None", "Some((time: Normal(mean: 2., std_dev: 1., min: 3., max: 1.)))");
+
+    assert!(valid.validate().is_ok());
+    assert!(negative_std_dev.validate().is_err());
+    assert!(inverted_bounds.validate().is_err());
+    assert!(invalid_service.validate().is_err());
+}
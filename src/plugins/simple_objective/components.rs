@@ -0,0 +1,30 @@
+use bevy::ecs::{component::Component, entity::Entity};
+
+use crate::utils::distribution::ParameterDistribution;
+
+/// Time agents spend at the objective before moving on (s)
+#[derive(Component, Clone, Copy)]
+pub struct ServiceTime(pub ParameterDistribution);
+
+/// Maximum number of agents served at the same time, unlimited when missing
+#[derive(Component, Clone, Copy)]
+pub struct ServiceCapacity(pub u32);
+
+/// Agent waiting at an objective with a `ServiceTime`
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub enum ServiceState {
+    /// Arrived while the objective was at capacity, `since` is the arrival time (s)
+    Queued { objective: Entity, since: f32 },
+
+    /// Being served, `remaining` is the time left (s)
+    InService { objective: Entity, remaining: f32 },
+}
+
+impl ServiceState {
+    pub fn objective(&self) -> Entity {
+        match *self {
+            ServiceState::Queued { objective, .. } => objective,
+            ServiceState::InService { objective, .. } => objective,
+        }
+    }
+}
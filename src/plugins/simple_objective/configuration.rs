@@ -0,0 +1,19 @@
+use bevy::ecs::resource::Resource;
+use serde::{Deserialize, Serialize};
+
+#[derive(Resource, Debug, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ObjectiveConfiguration {
+    pub arrival: ArrivalCriterion,
+}
+
+/// When an agent is considered to have reached its destination
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ArrivalCriterion {
+    /// The agent center is inside the objective shape
+    #[default]
+    Center,
+
+    /// The agent disk touches the objective shape
+    Touch,
+}
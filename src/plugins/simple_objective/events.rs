@@ -0,0 +1,56 @@
+use bevy::ecs::{entity::Entity, event::Event};
+
+use crate::plugins::{movement_tracking::components::PathLength, spawner::components::SpawnTime};
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ServiceStarted {
+    pub agent: Entity,
+    pub objective: Entity,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ServiceEnded {
+    pub agent: Entity,
+    pub objective: Entity,
+}
+
+/// Sent when an agent completes an objective, `final_objective` is set when it leaves the simulation
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct AgentArrived {
+    pub agent: Entity,
+    pub objective: Entity,
+    pub spawn_time: f32,     // s
+    pub arrival_time: f32,   // s
+    pub path_length: f32,    // m
+    pub mean_speed: f32,     // m/s
+    pub final_objective: bool,
+}
+
+impl AgentArrived {
+    /// Missing tracking components count as a spawn at time 0 and no distance travelled
+    pub fn new(
+        agent: Entity,
+        objective: Entity,
+        arrival_time: f32,
+        (spawn_time, path_length): (Option<&SpawnTime>, Option<&PathLength>),
+        final_objective: bool,
+    ) -> Self {
+        let spawn_time = spawn_time.map_or(0., |s| s.0);
+        let path_length = path_length.map_or(0., |p| p.0);
+        let travel_time = arrival_time - spawn_time;
+
+        Self {
+            agent,
+            objective,
+            spawn_time,
+            arrival_time,
+            path_length,
+            mean_speed: if travel_time > 0. { path_length / travel_time } else { 0. },
+            final_objective,
+        }
+    }
+
+    pub fn travel_time(&self) -> f32 {
+        self.arrival_time - self.spawn_time
+    }
+}
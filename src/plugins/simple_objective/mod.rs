@@ -1,2 +1,6 @@
+pub mod components;
+pub mod configuration;
+pub mod events;
 pub mod plugin;
+pub mod resources;
 pub mod systems;
\ No newline at end of file
@@ -1,14 +1,33 @@
-use bevy::{app::{App, Plugin, Update}, ecs::schedule::IntoScheduleConfigs};
+use bevy::{app::{App, Plugin}, ecs::schedule::IntoScheduleConfigs};
 
-use crate::plugins::kinematics::plugin::KinematicsSet;
+use crate::plugins::{
+    kinematics::plugin::KinematicsSet,
+    simulation_clock::plugin::{SimulationPostUpdate, SimulationUpdate},
+    social_foces_model::{plugin::SocialForcesSystemSet, system::apply_social_foces},
+};
 
-use super::systems::*;
+use crate::resources::rng::SimulationRng;
 
-pub struct SimpleObjective;
+use super::{configuration::ObjectiveConfiguration, events::*, resources::ArrivalStatistics, systems::*};
+
+#[derive(Default)]
+pub struct SimpleObjective {
+    pub configuration: ObjectiveConfiguration,
+}
 
 impl Plugin for SimpleObjective {
     fn build(&self, app: &mut App) {
 
-        app.add_systems(Update, check_if_agent_arrived_at_destination.after(KinematicsSet::ApplyVelocity));
+        app.insert_resource(self.configuration);
+
+        app.init_resource::<ArrivalStatistics>()
+            .init_resource::<SimulationRng>()
+            .add_event::<ServiceStarted>()
+            .add_event::<ServiceEnded>()
+            .add_event::<AgentArrived>();
+
+        app.add_systems(SimulationUpdate, (check_if_agent_arrived_at_destination, update_services).chain().after(KinematicsSet::ApplyVelocity))
+            .add_systems(SimulationPostUpdate, record_arrivals)
+            .add_systems(SimulationUpdate, hold_waiting_agents.in_set(SocialForcesSystemSet::ApplyForces).before(apply_social_foces));
     }
 }
@@ -0,0 +1,44 @@
+use bevy::ecs::resource::Resource;
+
+use super::events::AgentArrived;
+
+/// Every `AgentArrived` event of the run
+#[derive(Resource, Default)]
+pub struct ArrivalStatistics {
+    arrivals: Vec<AgentArrived>,
+}
+
+impl ArrivalStatistics {
+    pub fn add(&mut self, arrival: AgentArrived) {
+        self.arrivals.push(arrival);
+    }
+
+    pub fn arrivals(&self) -> &[AgentArrived] {
+        &self.arrivals
+    }
+
+    /// Arrivals of agents that left the simulation
+    pub fn exits(&self) -> impl Iterator<Item = &AgentArrived> {
+        self.arrivals.iter().filter(|a| a.final_objective)
+    }
+
+    /// Time between spawning and leaving the simulation of every agent that left (s)
+    pub fn evacuation_times(&self) -> Vec<f32> {
+        self.exits().map(AgentArrived::travel_time).collect()
+    }
+
+    /// Time the last agent left the simulation at (s)
+    pub fn last_exit_time(&self) -> Option<f32> {
+        self.exits().map(|a| a.arrival_time).reduce(f32::max)
+    }
+
+    pub fn mean_travel_time(&self) -> Option<f32> {
+        let times = self.evacuation_times();
+
+        if times.is_empty() {
+            return None;
+        }
+
+        Some(times.iter().sum::<f32>() / times.len() as f32)
+    }
+}
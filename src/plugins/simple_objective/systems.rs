@@ -1,30 +1,165 @@
-use bevy::prelude::*;
+use bevy::{platform::collections::HashMap, prelude::*};
 
-use crate::components::prelude::*;
+use crate::{
+    components::prelude::*,
+    plugins::{movement_tracking::components::PathLength, social_foces_model::components::MotivationForce, spawner::components::SpawnTime},
+    resources::{configuration::{SimulationConfiguration, SimulationTime}, rng::SimulationRng},
+};
+
+use super::{components::*, configuration::*, events::*, resources::*};
 
 pub fn check_if_agent_arrived_at_destination(
     mut commands: Commands,
-    agents: Query<(Entity, &Position, &Destination)>,
-    destinations: Query<(&Position, &Shape), With<Objective>>,
+    config: Res<ObjectiveConfiguration>,
+    time: Option<Res<SimulationTime>>,
+    mut arrived_events: EventWriter<AgentArrived>,
+    mut agents: Query<(Entity, &Position, &mut Destination, Option<&Shape>, Option<&mut Itinerary>, (Option<&SpawnTime>, Option<&PathLength>)), Without<ServiceState>>,
+    destinations: Query<(&Position, &Shape, Has<ServiceTime>), With<Objective>>,
 ) {
-    for (agent, agent_pos, agent_destination) in &agents {
+    let now = time.map_or(0., |time| time.elapsed());
+
+    for (agent, agent_pos, mut agent_destination, agent_shape, itinerary, travel) in &mut agents {
         let agent_position = agent_pos.value();
 
-        let (destination_pos, destination_shape) = match destinations.get(agent_destination.0) {
+        let (destination_pos, destination_shape, has_service) = match destinations.get(agent_destination.0) {
             Ok(e) => e,
             Err(_) => continue,
         };
 
-        match destination_shape {
-            Shape::Circle(radius) => {
-                let distance = (destination_pos.value() - agent_position).length() - radius;
-                if distance <= 0. {
-                    commands.entity(agent).despawn();
+        let reach = match (config.arrival, agent_shape) {
+            (ArrivalCriterion::Touch, Some(Shape::Circle(radius))) => *radius,
+            (_, _) => 0.,
+        };
+
+        if !has_arrived(destination_shape, destination_pos.value(), agent_position, reach) {
+            continue;
+        }
+
+        // Waiting agents stop where they arrived
+        if has_service {
+            commands.entity(agent).insert((
+                ServiceState::Queued {
+                    objective: agent_destination.0,
+                    since: now,
+                },
+                Speed::new(Vec2::ZERO),
+            ));
+            continue;
+        }
+
+        let arrival = leave_objective(&mut commands, agent, &mut agent_destination, itinerary, travel, now);
+        arrived_events.write(arrival);
+    }
+}
+
+/// Agents with an itinerary only leave the simulation at the final waypoint
+fn leave_objective(
+    commands: &mut Commands,
+    agent: Entity,
+    destination: &mut Destination,
+    itinerary: Option<Mut<Itinerary>>,
+    travel: (Option<&SpawnTime>, Option<&PathLength>),
+    now: f32,
+) -> AgentArrived {
+    let objective = destination.0;
+
+    let next = itinerary.and_then(|mut itinerary| itinerary.advance());
+
+    match next {
+        Some(next) => destination.0 = next,
+        None => commands.entity(agent).despawn(),
+    }
+
+    AgentArrived::new(agent, objective, now, travel, next.is_none())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_services(
+    mut commands: Commands,
+    config: Res<SimulationConfiguration>,
+    time: Option<Res<SimulationTime>>,
+    mut rng: ResMut<SimulationRng>,
+    mut started_events: EventWriter<ServiceStarted>,
+    mut ended_events: EventWriter<ServiceEnded>,
+    mut arrived_events: EventWriter<AgentArrived>,
+    mut agents: Query<(Entity, &mut ServiceState, &mut Destination, Option<&mut Itinerary>, (Option<&SpawnTime>, Option<&PathLength>))>,
+    services: Query<(&ServiceTime, Option<&ServiceCapacity>), With<Objective>>,
+) {
+    let now = time.map_or(0., |time| time.elapsed());
+
+    let mut occupancy: HashMap<Entity, u32> = HashMap::new();
+    let mut queue = Vec::new();
+
+    for (agent, mut state, mut destination, itinerary, travel) in &mut agents {
+        match *state {
+            ServiceState::InService { objective, remaining } => {
+                let remaining = remaining - config.simulation_time_step;
+
+                if remaining > 0. {
+                    *state = ServiceState::InService { objective, remaining };
+                    *occupancy.entry(objective).or_default() += 1;
+                    continue;
                 }
+
+                ended_events.write(ServiceEnded { agent, objective });
+                commands.entity(agent).remove::<ServiceState>();
+                let arrival = leave_objective(&mut commands, agent, &mut destination, itinerary, travel, now);
+                arrived_events.write(arrival);
             }
-            _ => todo!(),
+            ServiceState::Queued { objective, since } => queue.push((since, agent, objective)),
         }
     }
+
+    // First come, first served
+    queue.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+    for (_, agent, objective) in queue {
+        let Ok((service_time, capacity)) = services.get(objective) else {
+            continue;
+        };
+
+        let served = occupancy.entry(objective).or_default();
+
+        if capacity.is_some_and(|capacity| *served >= capacity.0) {
+            continue;
+        }
+
+        *served += 1;
+
+        let remaining = service_time.0.sample(&mut *rng);
+
+        if let Ok((_, mut state, _, _, _)) = agents.get_mut(agent) {
+            *state = ServiceState::InService { objective, remaining };
+        }
+
+        started_events.write(ServiceStarted { agent, objective });
+    }
+}
+
+pub fn record_arrivals(mut statistics: ResMut<ArrivalStatistics>, mut arrived_events: EventReader<AgentArrived>) {
+    for arrival in arrived_events.read() {
+        statistics.add(*arrival);
+    }
+}
+
+/// Waiting agents have no desired velocity, the motivation force brakes them
+///
+/// Without it nothing damps the pushes of the other agents and a waiting agent would drift away from the objective
+pub fn hold_waiting_agents(mut agents: Query<(&mut MotivationForce, &Speed), With<ServiceState>>) {
+    for (mut motivation_force, speed) in &mut agents {
+        motivation_force.0 = -speed.value();
+    }
+}
+
+/// Whether the point is inside the objective shape or closer than `reach` to its boundary
+pub fn has_arrived(shape: &Shape, shape_position: Vec2, point: Vec2, reach: f32) -> bool {
+    if point_in_shape(shape, shape_position, point) {
+        return true;
+    }
+
+    let (_, distance) = signed_distance_and_normal_to_sahpe(shape, shape_position, point);
+
+    distance <= reach
 }
 
 // #######
@@ -37,6 +172,8 @@ fn test_contact() {
 
     let mut app = App::new();
 
+    app.init_resource::<ObjectiveConfiguration>();
+    app.add_event::<AgentArrived>();
     app.add_systems(Update, check_if_agent_arrived_at_destination);
 
     let world = app.world_mut();
@@ -93,6 +230,8 @@ fn test_diferent_destination() {
 
     let mut app = App::new();
 
+    app.init_resource::<ObjectiveConfiguration>();
+    app.add_event::<AgentArrived>();
     app.add_systems(Update, check_if_agent_arrived_at_destination);
 
     let world = app.world_mut();
@@ -151,3 +290,386 @@ fn test_diferent_destination() {
     assert!(app.world().get::<Position>(agent_2).is_some());
     assert!(app.world().get::<Position>(agent_3).is_some());
 }
+
+#[test]
+fn test_concave_polygon_objective() {
+    // Setup
+
+    let mut app = App::new();
+
+    app.init_resource::<ObjectiveConfiguration>();
+    app.add_event::<AgentArrived>();
+    app.add_systems(Update, check_if_agent_arrived_at_destination);
+
+    let world = app.world_mut();
+
+    // U shaped room, open at the top between x = 1 and x = 3
+    let objective = world
+        .spawn((
+            Objective,
+            Shape::Polygon(vec![
+                Vec2::new(0., 0.),
+                Vec2::new(4., 0.),
+                Vec2::new(4., 4.),
+                Vec2::new(3., 4.),
+                Vec2::new(3., 1.),
+                Vec2::new(1., 1.),
+                Vec2::new(1., 4.),
+                Vec2::new(0., 4.),
+            ]),
+            Position::from(Vec2::new(10., 0.)),
+        ))
+        .id();
+
+    let arm_agent = world
+        .spawn((Agent, Position::from(Vec2::new(10.5, 3.)), Shape::Circle(0.3), Destination(objective)))
+        .id();
+
+    let base_agent = world
+        .spawn((Agent, Position::from(Vec2::new(12., 0.5)), Shape::Circle(0.3), Destination(objective)))
+        .id();
+
+    let notch_agent = world
+        .spawn((Agent, Position::from(Vec2::new(12., 3.)), Shape::Circle(0.3), Destination(objective)))
+        .id();
+
+    let outside_agent = world
+        .spawn((Agent, Position::from(Vec2::new(9.8, 2.)), Shape::Circle(0.3), Destination(objective)))
+        .id();
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    assert!(app.world().get::<Position>(arm_agent).is_none());
+    assert!(app.world().get::<Position>(base_agent).is_none());
+    assert!(app.world().get::<Position>(notch_agent).is_some());
+    assert!(app.world().get::<Position>(outside_agent).is_some());
+}
+
+#[test]
+fn test_touch_arrival_with_concave_polygon() {
+    // Setup
+
+    let mut app = App::new();
+
+    app.insert_resource(ObjectiveConfiguration { arrival: ArrivalCriterion::Touch });
+    app.add_event::<AgentArrived>();
+    app.add_systems(Update, check_if_agent_arrived_at_destination);
+
+    let world = app.world_mut();
+
+    let objective = world
+        .spawn((
+            Objective,
+            Shape::Polygon(vec![
+                Vec2::new(0., 0.),
+                Vec2::new(4., 0.),
+                Vec2::new(4., 4.),
+                Vec2::new(3., 4.),
+                Vec2::new(3., 1.),
+                Vec2::new(1., 1.),
+                Vec2::new(1., 4.),
+                Vec2::new(0., 4.),
+            ]),
+            Position::from(Vec2::ZERO),
+        ))
+        .id();
+
+    let touching_notch_agent = world
+        .spawn((Agent, Position::from(Vec2::new(2., 1.2)), Shape::Circle(0.3), Destination(objective)))
+        .id();
+
+    let center_notch_agent = world
+        .spawn((Agent, Position::from(Vec2::new(2., 3.)), Shape::Circle(0.3), Destination(objective)))
+        .id();
+
+    let touching_outside_agent = world
+        .spawn((Agent, Position::from(Vec2::new(-0.2, 2.)), Shape::Circle(0.3), Destination(objective)))
+        .id();
+
+    let far_agent = world
+        .spawn((Agent, Position::from(Vec2::new(-1., 2.)), Shape::Circle(0.3), Destination(objective)))
+        .id();
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    assert!(app.world().get::<Position>(touching_notch_agent).is_none());
+    assert!(app.world().get::<Position>(center_notch_agent).is_some());
+    assert!(app.world().get::<Position>(touching_outside_agent).is_none());
+    assert!(app.world().get::<Position>(far_agent).is_some());
+}
+
+#[test]
+fn test_itinerary_advances_until_final_waypoint() {
+    // Setup
+
+    let mut app = App::new();
+
+    app.init_resource::<ObjectiveConfiguration>();
+    app.add_event::<AgentArrived>();
+    app.add_systems(Update, check_if_agent_arrived_at_destination);
+
+    let world = app.world_mut();
+
+    let entrance = world
+        .spawn((Objective, Shape::Circle(1.), Position::from(Vec2::new(0., 0.))))
+        .id();
+
+    let gate = world
+        .spawn((Objective, Shape::Circle(1.), Position::from(Vec2::new(10., 0.))))
+        .id();
+
+    let platform = world
+        .spawn((Objective, Shape::Circle(1.), Position::from(Vec2::new(20., 0.))))
+        .id();
+
+    let agent = world
+        .spawn((
+            Agent,
+            Position::from(Vec2::ZERO),
+            Destination(entrance),
+            Itinerary::new(vec![entrance, gate, platform]),
+        ))
+        .id();
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    assert_eq!(app.world().get::<Destination>(agent).unwrap().0, gate);
+
+    // Act
+
+    app.world_mut().get_mut::<Position>(agent).unwrap().set_value(Vec2::new(10., 0.));
+    app.update();
+
+    // Assert
+
+    assert_eq!(app.world().get::<Destination>(agent).unwrap().0, platform);
+
+    // Act
+
+    app.update();
+    app.world_mut().get_mut::<Position>(agent).unwrap().set_value(Vec2::new(20., 0.));
+    app.update();
+
+    // Assert
+
+    assert!(app.world().get::<Position>(agent).is_none());
+}
+
+#[test]
+fn test_service_capacity_and_events() {
+    use crate::utils::distribution::ParameterDistribution;
+
+    // Setup
+
+    let mut app = App::new();
+
+    app.init_resource::<ObjectiveConfiguration>();
+    app.insert_resource(SimulationConfiguration { simulation_time_step: 1. });
+    app.insert_resource(SimulationRng::new(0));
+    app.add_event::<ServiceStarted>();
+    app.add_event::<ServiceEnded>();
+    app.add_event::<AgentArrived>();
+    app.add_systems(Update, (check_if_agent_arrived_at_destination, update_services).chain());
+
+    let world = app.world_mut();
+
+    let desk = world
+        .spawn((
+            Objective,
+            Shape::Circle(2.),
+            Position::from(Vec2::ZERO),
+            ServiceTime(ParameterDistribution::Constant(2.)),
+            ServiceCapacity(1),
+        ))
+        .id();
+
+    let exit = world
+        .spawn((Objective, Shape::Circle(1.), Position::from(Vec2::new(50., 0.))))
+        .id();
+
+    let agents: Vec<Entity> = (0..2)
+        .map(|i| {
+            world
+                .spawn((
+                    Agent,
+                    Position::from(Vec2::new(i as f32 * 0.5, 0.)),
+                    Destination(desk),
+                    Itinerary::new(vec![desk, exit]),
+                ))
+                .id()
+        })
+        .collect();
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    let states: Vec<ServiceState> = agents.iter().map(|a| *app.world().get::<ServiceState>(*a).unwrap()).collect();
+
+    assert_eq!(states[0], ServiceState::InService { objective: desk, remaining: 2. });
+    assert_eq!(states[1], ServiceState::Queued { objective: desk, since: 0. });
+
+    // Act
+
+    app.update();
+    app.update();
+
+    // Assert
+
+    assert!(app.world().get::<ServiceState>(agents[0]).is_none());
+    assert_eq!(app.world().get::<Destination>(agents[0]).unwrap().0, exit);
+    assert_eq!(app.world().get::<ServiceState>(agents[1]), Some(&ServiceState::InService { objective: desk, remaining: 2. }));
+
+    assert_eq!(app.world().resource::<Events<ServiceStarted>>().iter_current_update_events().count(), 1);
+    assert_eq!(app.world().resource::<Events<ServiceEnded>>().iter_current_update_events().count(), 1);
+}
+
+#[test]
+fn test_arrival_statistics() {
+    use crate::plugins::{movement_tracking::components::PathLength, spawner::components::SpawnTime};
+
+    // Setup
+
+    let mut app = App::new();
+
+    app.init_resource::<ObjectiveConfiguration>();
+    app.init_resource::<ArrivalStatistics>();
+    app.add_event::<AgentArrived>();
+    app.add_systems(Update, (check_if_agent_arrived_at_destination, record_arrivals).chain());
+
+    let world = app.world_mut();
+
+    let gate = world
+        .spawn((Objective, Shape::Circle(1.), Position::from(Vec2::ZERO)))
+        .id();
+
+    let exit = world
+        .spawn((Objective, Shape::Circle(1.), Position::from(Vec2::new(10., 0.))))
+        .id();
+
+    let mut time = SimulationTime::default();
+
+    for _ in 0..10 {
+        time.advance(1.);
+    }
+
+    world.insert_resource(time);
+
+    let agent = world
+        .spawn((
+            Agent,
+            Position::from(Vec2::ZERO),
+            Destination(gate),
+            Itinerary::new(vec![gate, exit]),
+            SpawnTime(2.),
+            PathLength(12.),
+        ))
+        .id();
+
+    // Act
+
+    app.update();
+    app.world_mut().get_mut::<Position>(agent).unwrap().set_value(Vec2::new(10., 0.));
+    app.update();
+
+    // Assert
+
+    let statistics = app.world().resource::<ArrivalStatistics>();
+
+    assert_eq!(statistics.arrivals().len(), 2);
+    assert!(!statistics.arrivals()[0].final_objective);
+
+    let exit_arrival = statistics.arrivals()[1];
+
+    assert_eq!(exit_arrival.objective, exit);
+    assert!(exit_arrival.final_objective);
+    assert_eq!(exit_arrival.travel_time(), 8.);
+    assert_eq!(exit_arrival.mean_speed, 1.5);
+    assert_eq!(statistics.evacuation_times(), vec![8.]);
+    assert!(app.world().get::<Position>(agent).is_none());
+}
+
+#[test]
+fn check_waiting_agents_stay_at_the_objective_during_their_service() {
+    use crate::{
+        plugins::{
+            kinematics::plugin::KinematicsPlugin,
+            simulation_clock::plugin::SimulationClockPlugin,
+            social_foces_model::{configuration::*, plugin::SocialForcesPlugin},
+        },
+        resources::configuration::SimulationMode,
+        utils::distribution::ParameterDistribution,
+    };
+
+    use super::plugin::SimpleObjective;
+
+    // Setup
+
+    let mut app = App::new();
+
+    app.add_plugins(SimulationClockPlugin { mode: SimulationMode::AsFastAsPossible })
+        .add_plugins(KinematicsPlugin)
+        .add_plugins(SimpleObjective::default())
+        .add_plugins(SocialForcesPlugin {
+            configuration: SocialForcesModelConfiguration {
+                agent_desired_speed: 1.34,
+                forces: ForceConfiguration {
+                    motivation_force: MotivationForceComputationStrategy::Direct,
+                    repulsion_force: RepulsionForceComputationStrategy::None,
+                    obstacle_force: ObstacleForceComputationStrategy::None,
+                },
+                ..Default::default()
+            },
+        })
+        .insert_resource(SimulationConfiguration { simulation_time_step: 0.1 });
+
+    let desk = app.world_mut()
+        .spawn((
+            Objective,
+            Shape::Circle(1.),
+            Position::from(Vec2::ZERO),
+            ServiceTime(ParameterDistribution::Constant(3.)),
+        ))
+        .id();
+
+    let agent = app.world_mut()
+        .spawn((Agent, Position::from(Vec2::new(-3., 0.)), Speed::new(Vec2::new(1.34, 0.)), Shape::Circle(0.3), Destination(desk)))
+        .id();
+
+    // Act
+
+    let mut waiting_positions = Vec::new();
+
+    for _ in 0..100 {
+        app.update();
+
+        let Some(position) = app.world().get::<Position>(agent) else {
+            break;
+        };
+
+        if app.world().get::<ServiceState>(agent).is_some() {
+            waiting_positions.push(position.value());
+        }
+    }
+
+    // Assert
+
+    // The agent arrives at the objective boundary with its desired speed, then waits for 3s before leaving
+    assert!(app.world().get::<Position>(agent).is_none());
+    assert!(waiting_positions.len() >= 29);
+    assert!(waiting_positions.iter().all(|position| position.length() <= 1.));
+}
@@ -1,6 +1,6 @@
 use bevy::{app::prelude::*, ecs::schedule::IntoScheduleConfigs, math::Rect};
 
-use crate::plugins::kinematics::plugin::KinematicsSet;
+use crate::plugins::{kinematics::plugin::KinematicsSet, simulation_clock::plugin::{SimulationPreUpdate, SimulationUpdate}};
 
 use super::{resources::*, systems::*};
 
@@ -13,8 +13,8 @@ impl Plugin for SimulationAreaPlugin {
 
         app.insert_resource(SimulationArea(self.simulation_area));
 
-        app.add_systems(Update, clamp_agent_position.after(KinematicsSet::ApplyVelocity))
-        .add_systems(First, remove_out_of_bounds_agents_on_creation)
+        app.add_systems(SimulationUpdate, clamp_agent_position.after(KinematicsSet::ApplyVelocity))
+        .add_systems(SimulationPreUpdate, remove_out_of_bounds_agents_on_creation)
         ;
     }
 }
\ No newline at end of file
@@ -0,0 +1,48 @@
+use bevy::{app::MainScheduleOrder, ecs::schedule::ScheduleLabel, prelude::*};
+
+use crate::resources::configuration::{SimulationMode, SimulationStep, SimulationTime};
+
+use super::systems::*;
+
+/// Drives the simulation schedules, decoupling simulated time from the number of rendered frames
+#[derive(Default)]
+pub struct SimulationClockPlugin {
+    pub mode: SimulationMode,
+}
+
+impl Plugin for SimulationClockPlugin {
+    fn build(&self, app: &mut App) {
+
+        app.insert_resource(self.mode)
+            .init_resource::<SimulationTime>()
+            .init_resource::<SimulationStep>();
+
+        app.init_schedule(SimulationLoop)
+            .init_schedule(SimulationPreUpdate)
+            .init_schedule(SimulationUpdate)
+            .init_schedule(SimulationPostUpdate);
+
+        app.world_mut()
+            .resource_mut::<MainScheduleOrder>()
+            .insert_after(PreUpdate, SimulationLoop);
+
+        app.add_systems(SimulationLoop, run_simulation_loop)
+            .add_systems(PreUpdate, handle_clock_inputs.run_if(resource_exists::<ButtonInput<KeyCode>>));
+    }
+}
+
+/// Runs the simulation schedules as many times as the current `SimulationMode` requires
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulationLoop;
+
+/// Runs once per simulation tick, before `SimulationUpdate`
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulationPreUpdate;
+
+/// Runs once per simulation tick
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulationUpdate;
+
+/// Runs once per simulation tick, after `SimulationUpdate`
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulationPostUpdate;
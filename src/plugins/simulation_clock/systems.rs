@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+use crate::resources::configuration::*;
+
+use super::plugin::{SimulationPostUpdate, SimulationPreUpdate, SimulationUpdate};
+
+/// Upper bound of ticks run on a single update in real time mode, avoids a spiral of death on slow frames
+const MAX_REAL_TIME_TICKS_PER_UPDATE: u32 = 8;
+
+pub fn run_simulation_loop(world: &mut World, mut accumulated_time: Local<f32>) {
+
+    let time_step = match world.get_resource::<SimulationConfiguration>() {
+        Some(config) => config.simulation_time_step,
+        None => return,
+    };
+
+    let ticks = match *world.resource::<SimulationMode>() {
+        SimulationMode::RealTime => {
+            let delta = world.resource::<Time>().delta_secs();
+            let max_accumulated_time = time_step * MAX_REAL_TIME_TICKS_PER_UPDATE as f32;
+
+            *accumulated_time = (*accumulated_time + delta).min(max_accumulated_time);
+
+            let ticks = (*accumulated_time / time_step).floor() as u32;
+            *accumulated_time -= ticks as f32 * time_step;
+
+            ticks
+        }
+        SimulationMode::AsFastAsPossible => 1,
+        SimulationMode::Paused => std::mem::take(&mut world.resource_mut::<SimulationStep>().0),
+    };
+
+    for _ in 0..ticks {
+        run_simulation_tick(world, time_step);
+    }
+}
+
+pub fn run_simulation_tick(world: &mut World, time_step: f32) {
+    world.run_schedule(SimulationPreUpdate);
+    world.run_schedule(SimulationUpdate);
+    world.run_schedule(SimulationPostUpdate);
+
+    world.resource_mut::<SimulationTime>().advance(time_step);
+}
+
+pub fn handle_clock_inputs(
+    mut mode: ResMut<SimulationMode>,
+    mut step: ResMut<SimulationStep>,
+    mut running_mode: Local<Option<SimulationMode>>,
+    keys: Res<ButtonInput<KeyCode>>,
+) {
+    if keys.just_pressed(KeyCode::Space) {
+        match *mode {
+            SimulationMode::Paused => *mode = running_mode.take().unwrap_or_default(),
+            current => {
+                *running_mode = Some(current);
+                *mode = SimulationMode::Paused;
+            }
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Period) && *mode == SimulationMode::Paused {
+        step.0 += 1;
+    }
+}
+
+// #######
+// Testing
+// #######
+
+#[cfg(test)]
+fn build_clock_app(mode: SimulationMode) -> App {
+    use super::plugin::SimulationClockPlugin;
+
+    let mut app = App::new();
+
+    app.add_plugins(SimulationClockPlugin { mode })
+        .insert_resource(SimulationConfiguration {
+            simulation_time_step: 0.5,
+        });
+
+    app
+}
+
+#[test]
+fn check_as_fast_as_possible_advances_one_tick_per_update() {
+
+    // Setup
+
+    let mut app = build_clock_app(SimulationMode::AsFastAsPossible);
+
+    // Act
+
+    app.update();
+    app.update();
+    app.update();
+
+    // Assert
+
+    let time = app.world().resource::<SimulationTime>();
+
+    assert_eq!(time.ticks(), 3);
+    assert_eq!(time.elapsed(), 1.5);
+}
+
+#[test]
+fn check_paused_only_advances_on_step() {
+
+    // Setup
+
+    let mut app = build_clock_app(SimulationMode::Paused);
+
+    // Act
+
+    app.update();
+    app.update();
+
+    app.world_mut().resource_mut::<SimulationStep>().0 += 1;
+
+    app.update();
+    app.update();
+
+    // Assert
+
+    let time = app.world().resource::<SimulationTime>();
+
+    assert_eq!(time.ticks(), 1);
+    assert_eq!(time.elapsed(), 0.5);
+}
@@ -7,4 +7,16 @@ pub struct MotivationForce(pub Vec2);
 pub struct ObstacleForce(pub Vec2);
 
 #[derive(Component, Default)]
-pub struct RepulsiveForce(pub Vec2);
\ No newline at end of file
+pub struct RepulsiveForce(pub Vec2);
+
+/// Speed the agent walks at when unobstructed (m/s)
+#[derive(Component, Clone, Copy)]
+pub struct DesiredSpeed(pub f32);
+
+/// Mass of the agent (Kg)
+#[derive(Component, Clone, Copy)]
+pub struct Mass(pub f32);
+
+/// Upper bound of the agent speed (m/s)
+#[derive(Component, Clone, Copy)]
+pub struct MaxSpeed(pub f32);
@@ -1,10 +1,13 @@
 use bevy::ecs::resource::Resource;
+use serde::{Deserialize, Serialize};
 
-#[derive(Resource, Clone, Copy)]
+#[derive(Resource, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SocialForcesModelConfiguration {
-    // Agent data
+    // Agent data, defaults for agents without their own parameters
     pub agent_desired_speed: f32,   // m/s
     pub agent_mass: f32,            // Kg
+    pub agent_max_speed_factor: f32, // Max speed relative to the desired speed
     //pub agent_radius: f32 ,       // m
 
     // Constants
@@ -13,6 +16,10 @@ pub struct SocialForcesModelConfiguration {
     pub k: f32,
     pub kappa: f32,
 
+    // Neighbour search, only used by the spatial hash strategies
+    pub repulsion_cutoff: f32,      // m, between agent centers
+    pub obstacle_cutoff: f32,       // m, between the agent body and the obstacle
+
     pub forces: ForceConfiguration,
 }
 
@@ -21,24 +28,28 @@ impl Default for SocialForcesModelConfiguration {
         Self {
             agent_desired_speed: 0.8,
             agent_mass: 80.,
+            agent_max_speed_factor: 1.,
             //agent_radius: 0.3,
             a: 2000.,   // N
             b: 0.08,    // m
             k: 120000., // kg/s²
             kappa: 240000.,
+            repulsion_cutoff: 2.,
+            obstacle_cutoff: 2.,
             forces: ForceConfiguration::default(),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, Default)]
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ForceConfiguration {
     pub motivation_force: MotivationForceComputationStrategy,
     pub repulsion_force: RepulsionForceComputationStrategy,
     pub obstacle_force: ObstacleForceComputationStrategy,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 pub enum MotivationForceComputationStrategy {
     None,
     Direct,
@@ -46,16 +57,46 @@ pub enum MotivationForceComputationStrategy {
     FlowFieldPathFinding,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 pub enum RepulsionForceComputationStrategy {
     None,
     #[default]
     Direct,
+
+    /// Direct, with the body compression and sliding friction terms of overlapping agents
+    DirectWithContact,
+
+    /// Only agents closer than `repulsion_cutoff` interact, found through an `AgentSpatialHash`
+    SpatialHash,
+
+    /// SpatialHash, with the body compression and sliding friction terms of overlapping agents
+    SpatialHashWithContact,
+}
+
+impl RepulsionForceComputationStrategy {
+    pub fn with_contact(&self) -> bool {
+        matches!(self, Self::DirectWithContact | Self::SpatialHashWithContact)
+    }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 pub enum ObstacleForceComputationStrategy {
     None,
     #[default]
     Direct,
+
+    /// Direct, with the body compression and sliding friction terms of agents touching obstacles
+    DirectWithContact,
+
+    /// Only obstacle edges closer than `obstacle_cutoff` interact, found through an `ObstacleEdgeGrid`
+    EdgeGrid,
+
+    /// EdgeGrid, with the body compression and sliding friction terms of agents touching obstacles
+    EdgeGridWithContact,
+}
+
+impl ObstacleForceComputationStrategy {
+    pub fn with_contact(&self) -> bool {
+        matches!(self, Self::DirectWithContact | Self::EdgeGridWithContact)
+    }
 }
@@ -1,4 +1,5 @@
 pub mod components;
 pub mod plugin;
+pub mod resources;
 pub mod system;
 pub mod configuration;
\ No newline at end of file
@@ -4,10 +4,12 @@ use bevy::{
 };
 
 use crate::plugins::{
-    flow_field_pathfinding::plugin::FlowFieldSystemSet, kinematics::plugin::KinematicsSet,
+    flow_field_pathfinding::plugin::FlowFieldSystemSet,
+    kinematics::plugin::KinematicsSet,
+    simulation_clock::plugin::{SimulationPreUpdate, SimulationUpdate},
 };
 
-use super::{components::*, configuration::*, system::*};
+use super::{components::*, configuration::*, resources::*, system::*};
 
 #[derive(Default)]
 pub struct SocialForcesPlugin {
@@ -19,11 +21,11 @@ impl Plugin for SocialForcesPlugin {
         app.insert_resource(self.configuration);
 
         app.configure_sets(
-            Update,
+            SimulationUpdate,
             SocialForcesSystemSet::ComputeForces.before(KinematicsSet::ApplyVelocity),
         );
         app.configure_sets(
-            Update,
+            SimulationUpdate,
             (
                 SocialForcesSystemSet::ComputeForces,
                 SocialForcesSystemSet::ApplyForces,
@@ -33,22 +35,23 @@ impl Plugin for SocialForcesPlugin {
                 .chain(),
         );
 
-        app.add_systems(PreUpdate, add_force_to_agents::<MotivationForce>)
-            .add_systems(PreUpdate, add_force_to_agents::<ObstacleForce>)
-            .add_systems(PreUpdate, add_force_to_agents::<RepulsiveForce>);
+        app.add_systems(SimulationPreUpdate, add_force_to_agents::<MotivationForce>)
+            .add_systems(SimulationPreUpdate, add_force_to_agents::<ObstacleForce>)
+            .add_systems(SimulationPreUpdate, add_force_to_agents::<RepulsiveForce>)
+            .add_systems(SimulationPreUpdate, add_parameters_to_agents);
 
         match self.configuration.forces.motivation_force {
             MotivationForceComputationStrategy::None => (),
             MotivationForceComputationStrategy::Direct => {
                 app.add_systems(
-                    Update,
+                    SimulationUpdate,
                     compute_motivation_force_via_absolute_direction
                         .in_set(SocialForcesSystemSet::ComputeForces),
                 );
             }
             MotivationForceComputationStrategy::FlowFieldPathFinding => {
                 app.add_systems(
-                    Update,
+                    SimulationUpdate,
                     compute_motivation_force_via_floor_field
                         .in_set(SocialForcesSystemSet::ComputeForces)
                         .after(FlowFieldSystemSet::ComputeFields),
@@ -58,35 +61,65 @@ impl Plugin for SocialForcesPlugin {
 
         match self.configuration.forces.repulsion_force {
             RepulsionForceComputationStrategy::None => (),
-            RepulsionForceComputationStrategy::Direct => {
+            RepulsionForceComputationStrategy::Direct
+            | RepulsionForceComputationStrategy::DirectWithContact => {
                 app.add_systems(
-                    Update,
+                    SimulationUpdate,
                     compute_repulsive_forces.in_set(SocialForcesSystemSet::ComputeForces),
                 );
             }
+            RepulsionForceComputationStrategy::SpatialHash
+            | RepulsionForceComputationStrategy::SpatialHashWithContact => {
+                app.insert_resource(AgentSpatialHash::new(self.configuration.repulsion_cutoff));
+                app.add_systems(
+                    SimulationUpdate,
+                    (rebuild_agent_spatial_hash, compute_repulsive_forces_via_spatial_hash)
+                        .chain()
+                        .in_set(SocialForcesSystemSet::ComputeForces),
+                );
+            }
         }
 
         match self.configuration.forces.obstacle_force {
             ObstacleForceComputationStrategy::None => (),
-            ObstacleForceComputationStrategy::Direct => {
+            ObstacleForceComputationStrategy::Direct
+            | ObstacleForceComputationStrategy::DirectWithContact => {
                 app.add_systems(
-                    Update,
+                    SimulationUpdate,
                     compute_obstacle_force.in_set(SocialForcesSystemSet::ComputeForces),
                 );
             }
+            ObstacleForceComputationStrategy::EdgeGrid
+            | ObstacleForceComputationStrategy::EdgeGridWithContact => {
+                add_obstacle_edge_grid(app, self.configuration.obstacle_cutoff);
+                app.add_systems(
+                    SimulationUpdate,
+                    compute_obstacle_force_via_edge_grid.in_set(SocialForcesSystemSet::ComputeForces),
+                );
+            }
         }
 
         app.add_systems(
-            Update,
+            SimulationUpdate,
             apply_social_foces.in_set(SocialForcesSystemSet::ApplyForces),
         )
         .add_systems(
-            Update,
+            SimulationUpdate,
             agent_max_speed.in_set(SocialForcesSystemSet::ApplyConstraints),
         );
     }
 }
 
+/// Adds the `ObstacleEdgeGrid`, rebuilt before the simulation update, only the first plugin using it picks its cell size
+pub fn add_obstacle_edge_grid(app: &mut App, cell_size: f32) {
+    if app.world().contains_resource::<ObstacleEdgeGrid>() {
+        return;
+    }
+
+    app.insert_resource(ObstacleEdgeGrid::new(cell_size))
+        .add_systems(SimulationPreUpdate, rebuild_obstacle_edge_grid);
+}
+
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SocialForcesSystemSet {
     ComputeForces,
@@ -0,0 +1,249 @@
+use bevy::{
+    ecs::{entity::Entity, resource::Resource},
+    math::{IVec2, Vec2},
+    platform::collections::HashMap,
+};
+
+use crate::components::prelude::*;
+
+/// Snapshot of the agent data needed to compute the repulsive forces
+#[derive(Clone, Copy)]
+pub struct AgentSpatialHashEntry {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub speed: Vec2,
+    pub radius: f32,
+}
+
+/// Uniform grid over the agent positions, rebuilt every tick, used to find the neighbours of an agent
+#[derive(Resource)]
+pub struct AgentSpatialHash {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<AgentSpatialHashEntry>>,
+}
+
+impl AgentSpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+    }
+
+    pub fn insert(&mut self, entry: AgentSpatialHashEntry) {
+        let cell = self.get_cell(entry.position);
+        self.cells.entry(cell).or_default().push(entry);
+    }
+
+    pub fn get_cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    /// Entries whose center is within `radius` of `position`, including the one at `position` itself
+    pub fn neighbours(&self, position: Vec2, radius: f32) -> impl Iterator<Item = &AgentSpatialHashEntry> {
+        let center = self.get_cell(position);
+        let reach = (radius / self.cell_size).ceil() as i32;
+
+        (-reach..=reach)
+            .flat_map(move |x| (-reach..=reach).map(move |y| center + IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter(move |entry| entry.position.distance_squared(position) <= radius * radius)
+    }
+}
+
+/// Piece of an obstacle boundary, in world coordinates
+#[derive(Clone, Copy)]
+pub enum ObstacleElement {
+    Segment(Vec2, Vec2),
+    Circle(Vec2, f32),
+}
+
+impl ObstacleElement {
+    /// Same convention as `signed_distance_and_normal_to_sahpe`
+    pub fn distance_and_normal(&self, point: Vec2) -> (Vec2, f32) {
+        match *self {
+            ObstacleElement::Segment(a, b) => distance_and_normal_to_segment(a, b, point),
+            ObstacleElement::Circle(center, radius) => (point - center, (point - center).length() - radius),
+        }
+    }
+}
+
+/// Uniform grid over the obstacle edges, rebuilt when obstacles change
+#[derive(Resource)]
+pub struct ObstacleEdgeGrid {
+    cell_size: f32,
+    // Elements of the same obstacle are contiguous
+    elements: Vec<(Entity, ObstacleElement)>,
+    cells: HashMap<IVec2, Vec<usize>>,
+}
+
+impl ObstacleEdgeGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            elements: Vec::new(),
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.elements.clear();
+        self.cells.clear();
+    }
+
+    pub fn get_cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    pub fn insert_obstacle(&mut self, entity: Entity, position: Vec2, shape: &Shape) {
+        match shape {
+            Shape::Circle(radius) => self.insert_element(entity, ObstacleElement::Circle(position, *radius)),
+            Shape::Polygon(points) => {
+                for i in 0..points.len() {
+                    let a = position + points[i];
+                    let b = position + points[(i + 1) % points.len()];
+
+                    // Closed polygons repeat their first point, which makes an edge without length
+                    if (b - a).length_squared() <= f32::EPSILON {
+                        continue;
+                    }
+
+                    self.insert_element(entity, ObstacleElement::Segment(a, b));
+                }
+            }
+        }
+    }
+
+    fn insert_element(&mut self, entity: Entity, element: ObstacleElement) {
+        let index = self.elements.len();
+        self.elements.push((entity, element));
+
+        let (min, max) = match element {
+            ObstacleElement::Segment(a, b) => (a.min(b), a.max(b)),
+            ObstacleElement::Circle(center, radius) => (center - radius, center + radius),
+        };
+
+        let half_diagonal = self.cell_size * std::f32::consts::FRAC_1_SQRT_2;
+
+        let min_cell = self.get_cell(min);
+        let max_cell = self.get_cell(max);
+
+        for x in min_cell.x..=max_cell.x {
+            for y in min_cell.y..=max_cell.y {
+                let cell = IVec2::new(x, y);
+                let cell_center = (cell.as_vec2() + 0.5) * self.cell_size;
+
+                let distance = element.distance_and_normal(cell_center).1;
+
+                // Skip the cells of the bounding box that a diagonal segment does not cross
+                if distance.is_nan() || distance > half_diagonal {
+                    continue;
+                }
+
+                self.cells.entry(cell).or_default().push(index);
+            }
+        }
+    }
+
+    /// Normal and distance to the closest element of every obstacle with an element within `radius` of `position`
+    pub fn closest_per_obstacle(&self, position: Vec2, radius: f32) -> Vec<(Entity, Vec2, f32)> {
+        let reach = Vec2::splat(radius);
+        let min_cell = self.get_cell(position - reach);
+        let max_cell = self.get_cell(position + reach);
+
+        let mut candidates: Vec<usize> = (min_cell.x..=max_cell.x)
+            .flat_map(|x| (min_cell.y..=max_cell.y).map(move |y| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let mut closest: Vec<(Entity, Vec2, f32)> = Vec::new();
+
+        for index in candidates {
+            let (entity, element) = &self.elements[index];
+            let (normal, distance) = element.distance_and_normal(position);
+
+            if distance.is_nan() || distance > radius {
+                continue;
+            }
+
+            match closest.last_mut() {
+                Some(last) if last.0 == *entity => {
+                    if distance < last.2 {
+                        *last = (*entity, normal, distance);
+                    }
+                }
+                _ => closest.push((*entity, normal, distance)),
+            }
+        }
+
+        closest
+    }
+}
+
+// #######
+// Testing
+// #######
+
+#[test]
+fn check_neighbours_within_radius() {
+    let mut hash = AgentSpatialHash::new(1.);
+
+    let positions = [Vec2::new(0., 0.), Vec2::new(0.9, 0.), Vec2::new(1.5, 1.5), Vec2::new(-3., 0.)];
+
+    for (i, position) in positions.iter().enumerate() {
+        hash.insert(AgentSpatialHashEntry {
+            entity: Entity::from_raw(i as u32),
+            position: *position,
+            speed: Vec2::ZERO,
+            radius: 0.3,
+        });
+    }
+
+    let mut found: Vec<u32> = hash.neighbours(Vec2::ZERO, 1.).map(|e| e.entity.index()).collect();
+    found.sort();
+
+    assert_eq!(found, vec![0, 1]);
+
+    let mut found: Vec<u32> = hash.neighbours(Vec2::ZERO, 2.5).map(|e| e.entity.index()).collect();
+    found.sort();
+
+    assert_eq!(found, vec![0, 1, 2]);
+}
+
+#[test]
+fn check_closed_polygons_have_no_degenerate_edges() {
+
+    // Setup
+
+    let mut grid = ObstacleEdgeGrid::new(2.);
+
+    // Closed like the polygons of the scenario files, the last point repeats the first one
+    let wall = Shape::Polygon(vec![Vec2::new(0., 0.), Vec2::new(2., 0.), Vec2::new(2., 9.), Vec2::new(0., 9.), Vec2::new(0., 0.)]);
+
+    // Act
+
+    grid.insert_obstacle(Entity::from_raw(0), Vec2::new(0., 1.5), &wall);
+
+    // Assert
+
+    assert_eq!(grid.elements.len(), 4);
+
+    // Next to the first point of the polygon
+    let closest = grid.closest_per_obstacle(Vec2::new(-0.4, 1.), 2.);
+
+    assert_eq!(closest.len(), 1);
+    assert!(closest[0].1.is_finite() && closest[0].2.is_finite());
+    assert!((closest[0].2 - Vec2::new(0.4, 0.5).length()).abs() < 1e-5);
+}
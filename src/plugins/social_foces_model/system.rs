@@ -2,7 +2,7 @@ use bevy::{math::vec2, prelude::*};
 
 use crate::{components::prelude::*, plugins::flow_field_pathfinding::resources::{EntityMultiField, Grid2D}};
 
-use super::{components::*, configuration::*};
+use super::{components::*, configuration::*, resources::*};
 
 // Setup
 
@@ -15,19 +15,35 @@ pub fn add_force_to_agents<T> (
     }
 }
 
+pub fn add_parameters_to_agents(
+    config: Res<SocialForcesModelConfiguration>,
+    mut commands: Commands,
+    query: Query<(Entity, Option<&DesiredSpeed>), (With<Agent>, Or<(Without<DesiredSpeed>, Without<Mass>, Without<MaxSpeed>)>)>
+) {
+    for (entity, desired_speed) in query.iter() {
+        let desired_speed = desired_speed.map_or(config.agent_desired_speed, |s| s.0);
+
+        commands.entity(entity).insert_if_new((
+            DesiredSpeed(desired_speed),
+            Mass(config.agent_mass),
+            MaxSpeed(desired_speed * config.agent_max_speed_factor),
+        ));
+    }
+}
+
 // Update
 
 pub fn compute_obstacle_force(
     config: Res<SocialForcesModelConfiguration>,
-    mut agents: Query<(&mut ObstacleForce, &Position, &Shape), With<Agent>>,
+    mut agents: Query<(&mut ObstacleForce, &Position, &Speed, &Shape), With<Agent>>,
     obstacles: Query<(&Position, &Shape), With<Obstacle>>,
 ) {
 
-    for (mut force, _, _)in &mut agents{
-        force.0 = vec2(0., 0.)
-    }
+    let contact = config.forces.obstacle_force.with_contact();
     
-    for (mut obstacle_force, agent_pos, shape) in &mut agents {
+    agents.par_iter_mut().for_each(|(mut obstacle_force, agent_pos, agent_speed, shape)| {
+        obstacle_force.0 = vec2(0., 0.);
+
         for (obstacle_pos, obstacle_shape) in &obstacles {
             
             let (n, dist) = signed_distance_and_normal_to_sahpe(
@@ -38,50 +54,71 @@ pub fn compute_obstacle_force(
         
             let effective_distance = dist - shape.get_rectangle_with_center(Vec2::ZERO).width() / 2.;
 
-            let g = 0.;
-
-            let n = n.normalize();
-            let t = Vec2::new(-n.y, n.x);
+            obstacle_force.0 += obstacle_interaction_force(&config, contact, n, effective_distance, agent_speed.value());
+        }
+    });
+}
 
-            let repulsive_factor = config.a * (-effective_distance / config.b).exp();
-            let contact_factor = config.k * g * effective_distance;
+pub fn rebuild_obstacle_edge_grid(
+    mut edge_grid: ResMut<ObstacleEdgeGrid>,
+    mut removed_obstacles: RemovedComponents<Obstacle>,
+    obstacles: Query<(Entity, Ref<Position>, Ref<Shape>), With<Obstacle>>,
+) {
+    let changed = obstacles.iter().any(|(_, position, shape)| position.is_changed() || shape.is_changed());
 
-            let pushing_force = (repulsive_factor + contact_factor) * n;
-            let sliding_force = config.kappa * g * effective_distance * t;
+    if !changed && removed_obstacles.read().count() == 0 {
+        return;
+    }
 
-            let final_force = pushing_force + sliding_force;
+    edge_grid.clear();
 
-            obstacle_force.0 += Vec2::new(final_force.x, final_force.y);
-        }
+    for (entity, position, shape) in &obstacles {
+        edge_grid.insert_obstacle(entity, position.value(), &shape);
     }
 }
 
-pub fn apply_social_foces(
+pub fn compute_obstacle_force_via_edge_grid(
     config: Res<SocialForcesModelConfiguration>,
-    mut agents: Query<(&mut Speed, &ObstacleForce, &MotivationForce, &RepulsiveForce), With<Agent>>,
+    edge_grid: Res<ObstacleEdgeGrid>,
+    mut agents: Query<(&mut ObstacleForce, &Position, &Speed, &Shape), With<Agent>>,
 ) {
-    for (mut agent_speed, obstacle_force, motivation_force, repulsive_force) in &mut agents {
+    let contact = config.forces.obstacle_force.with_contact();
+
+    agents.par_iter_mut().for_each(|(mut obstacle_force, agent_pos, agent_speed, shape)| {
+        let radius = shape.get_rectangle_with_center(Vec2::ZERO).width() / 2.;
+
+        obstacle_force.0 = edge_grid
+            .closest_per_obstacle(agent_pos.value(), config.obstacle_cutoff + radius)
+            .into_iter()
+            .map(|(_, n, dist)| obstacle_interaction_force(&config, contact, n, dist - radius, agent_speed.value()))
+            .sum();
+    });
+}
+
+pub fn apply_social_foces(
+    mut agents: Query<(&mut Speed, &ObstacleForce, &MotivationForce, &RepulsiveForce, &Mass), With<Agent>>,
+) {
+    agents.par_iter_mut().for_each(|(mut agent_speed, obstacle_force, motivation_force, repulsive_force, mass)| {
         let previous_speed = agent_speed.value().clone();
 
-        agent_speed.set_value(previous_speed + motivation_force.0 + (obstacle_force.0 + repulsive_force.0) / config.agent_mass);
+        agent_speed.set_value(previous_speed + motivation_force.0 + (obstacle_force.0 + repulsive_force.0) / mass.0);
 
-        *agent_speed += (obstacle_force.0 + (obstacle_force.0 + repulsive_force.0) / config.agent_mass).into();
-    }
+        *agent_speed += (obstacle_force.0 + (obstacle_force.0 + repulsive_force.0) / mass.0).into();
+    });
 }
 
 pub fn compute_motivation_force_via_floor_field(
-    config: Res<SocialForcesModelConfiguration>,
     vector_multi_field: ResMut<EntityMultiField<Vec2>>, 
-    mut agents: Query<(&mut MotivationForce, &Position, &Speed, &Destination), With<Agent>>
+    mut agents: Query<(&mut MotivationForce, &Position, &Speed, &Destination, &DesiredSpeed), With<Agent>>
 ){
     
-    for (mut motivation_force, position, agent_speed, &destination) in &mut agents {
+    agents.par_iter_mut().for_each(|(mut motivation_force, position, agent_speed, &destination, desired_speed)| {
         let pos = position.value();
         let vector_field = vector_multi_field.get(&destination.0).expect("Grid map not found in grid multi map");
 
         let cell = match vector_field.get_cell(&pos) {
             Some(v) => v,
-            None => continue,
+            None => return,
         };
 
         let base_vector = cell
@@ -95,87 +132,531 @@ pub fn compute_motivation_force_via_floor_field(
                 None
             }
         ).map(|(c, v)| v/(vector_field.get_coord(*c) - pos).length_squared())
-        .fold(Vec2::ZERO, |acc, v| acc + v).normalize() * config.agent_desired_speed;
+        .fold(Vec2::ZERO, |acc, v| acc + v).normalize() * desired_speed.0;
 
         if base_vector.is_nan(){
-            continue;
+            return;
         }
         
         let final_force = base_vector - agent_speed.value();
 
         motivation_force.0 = final_force;
-    }
+    });
 }
 
 pub fn compute_motivation_force_via_absolute_direction(
-    config: Res<SocialForcesModelConfiguration>,
-    mut agents: Query<(&mut MotivationForce, &Position, &Speed, &Destination), With<Agent>>,
+    mut agents: Query<(&mut MotivationForce, &Position, &Speed, &Destination, &DesiredSpeed), With<Agent>>,
     objectives: Query<&Position, With<Objective>>
 ){
-    for (mut motivation_force, agent_position, agent_speed, destination) in agents.iter_mut() {
+    agents.par_iter_mut().for_each(|(mut motivation_force, agent_position, agent_speed, destination, desired_speed)| {
         if let Ok(objective_position) = objectives.get(destination.0){
-            let base_vector = (objective_position.value() - agent_position.value()).normalize() * config.agent_desired_speed;
+            let base_vector = (objective_position.value() - agent_position.value()).normalize() * desired_speed.0;
 
             if base_vector.is_nan() || base_vector.length() < f32::EPSILON{
-                continue;
+                return;
             }
 
             let final_force = base_vector - agent_speed.value();
 
             motivation_force.0 = final_force;
         }
-    }
+    });
 }
 
 pub fn compute_repulsive_forces(
     config: Res<SocialForcesModelConfiguration>,
-    mut agents: Query<(&mut RepulsiveForce, &Position, &Shape), With<Agent>>
+    mut agents: Query<(&mut RepulsiveForce, &Position, &Speed, &Shape), With<Agent>>
 ) {
     
-    for (mut force, _, _)in &mut agents{
+    for (mut force, _, _, _)in &mut agents{
         force.0 = vec2(0., 0.)
     }
+
+    let contact = config.forces.repulsion_force.with_contact();
     
     let mut combinations = agents.iter_combinations_mut();
 
-    let g = 0.;
+    while let Some([(mut force_1, position_1, speed_1, shape_1), (mut force_2, position_2, speed_2, shape_2)]) = combinations.fetch_next() {
 
-    while let Some([(mut force_1, position_1, shape_1), (mut force_2, position_2, shape_2)]) = combinations.fetch_next() {
+        let combined_radius = (shape_1.get_rectangle_with_center(Vec2::ZERO).width() + shape_2.get_rectangle_with_center(Vec2::ZERO).width()) / 2.;
 
-        let combined_radius = match (shape_1, shape_2) {
-            (Shape::Circle(r1), Shape::Circle(r2)) => r1 + r2,
-            (_, _) => todo!()
-        };
+        let final_force = agent_interaction_force(
+            &config,
+            contact,
+            position_1.value() - position_2.value(),
+            speed_2.value() - speed_1.value(),
+            combined_radius,
+        );
 
-        let effective_distance = (position_2.value() - position_1.value()).length() - combined_radius;
-
-
-        let n = (position_1.value() - position_2.value())
-        .normalize();
-
-        let t = Vec2::new(-n.y, n.x);
-
-        let repulsive_factor = config.a * (-effective_distance / config.b).exp();
-        let contact_factor = config.k * g * effective_distance;
+        force_1.0 += final_force;
+        force_2.0 += -final_force;
+    }
+}
 
-        let pushing_force = (repulsive_factor + contact_factor) * n;
-        let sliding_force = config.kappa * g * effective_distance * t;
+pub fn rebuild_agent_spatial_hash(
+    mut spatial_hash: ResMut<AgentSpatialHash>,
+    agents: Query<(Entity, &Position, &Speed, &Shape), With<Agent>>
+) {
+    spatial_hash.clear();
 
-        let final_force = pushing_force + sliding_force;
+    for (entity, position, speed, shape) in &agents {
+        let radius = shape.get_rectangle_with_center(Vec2::ZERO).width() / 2.;
 
-        force_1.0 += Vec2::new(final_force.x, final_force.y);
-        force_2.0 += -Vec2::new(final_force.x, final_force.y);
+        spatial_hash.insert(AgentSpatialHashEntry {
+            entity,
+            position: position.value(),
+            speed: speed.value(),
+            radius,
+        });
     }
 }
 
-pub fn agent_max_speed(config: Res<SocialForcesModelConfiguration>, mut agents: Query<&mut Speed, With<Agent>>) {
-    for mut speed in &mut agents {
-        let mut new_speed = speed.value().clamp_length_max(config.agent_desired_speed);
+pub fn compute_repulsive_forces_via_spatial_hash(
+    config: Res<SocialForcesModelConfiguration>,
+    spatial_hash: Res<AgentSpatialHash>,
+    mut agents: Query<(Entity, &mut RepulsiveForce, &Position, &Speed, &Shape), With<Agent>>
+) {
+    let contact = config.forces.repulsion_force.with_contact();
+
+    agents.par_iter_mut().for_each(|(entity, mut force, position, speed, shape)| {
+        let radius = shape.get_rectangle_with_center(Vec2::ZERO).width() / 2.;
+
+        force.0 = spatial_hash
+            .neighbours(position.value(), config.repulsion_cutoff)
+            .filter(|neighbour| neighbour.entity != entity)
+            .map(|neighbour| agent_interaction_force(
+                &config,
+                contact,
+                position.value() - neighbour.position,
+                neighbour.speed - speed.value(),
+                radius + neighbour.radius,
+            ))
+            .sum();
+    });
+}
+
+pub fn agent_max_speed(mut agents: Query<(&mut Speed, &MaxSpeed), With<Agent>>) {
+    agents.par_iter_mut().for_each(|(mut speed, max_speed)| {
+        let mut new_speed = speed.value().clamp_length_max(max_speed.0);
         
         if new_speed.is_nan() {
             new_speed = Vec2::ZERO;
         }
 
         speed.set_value(new_speed);
+    });
+}
+
+// Forces
+
+/// Helbing's g(x) = max(0, -x), only non zero when the bodies overlap (m)
+pub fn contact_overlap(effective_distance: f32) -> f32 {
+    (-effective_distance).max(0.)
+}
+
+/// Force exerted on an agent by a neighbour.
+///
+/// `offset` goes from the neighbour to the agent and `relative_speed` is the neighbour speed minus the agent speed.
+/// The body compression and sliding friction terms are only applied when `contact` is set.
+pub fn agent_interaction_force(
+    config: &SocialForcesModelConfiguration,
+    contact: bool,
+    offset: Vec2,
+    relative_speed: Vec2,
+    combined_radius: f32,
+) -> Vec2 {
+    let effective_distance = offset.length() - combined_radius;
+
+    let g = if contact { contact_overlap(effective_distance) } else { 0. };
+
+    let n = offset.normalize();
+    let t = Vec2::new(-n.y, n.x);
+
+    let repulsive_factor = config.a * (-effective_distance / config.b).exp();
+    let contact_factor = config.k * g;
+
+    let pushing_force = (repulsive_factor + contact_factor) * n;
+    let sliding_force = config.kappa * g * relative_speed.dot(t) * t;
+
+    pushing_force + sliding_force
+}
+
+/// Force exerted on an agent by an obstacle.
+///
+/// `normal` points from the obstacle to the agent.
+/// The body compression and sliding friction terms are only applied when `contact` is set.
+pub fn obstacle_interaction_force(
+    config: &SocialForcesModelConfiguration,
+    contact: bool,
+    normal: Vec2,
+    effective_distance: f32,
+    agent_speed: Vec2,
+) -> Vec2 {
+    let g = if contact { contact_overlap(effective_distance) } else { 0. };
+
+    let n = normal.normalize();
+    let t = Vec2::new(-n.y, n.x);
+
+    let repulsive_factor = config.a * (-effective_distance / config.b).exp();
+    let contact_factor = config.k * g;
+
+    let pushing_force = (repulsive_factor + contact_factor) * n;
+    let sliding_force = -config.kappa * g * agent_speed.dot(t) * t;
+
+    pushing_force + sliding_force
+}
+
+// #######
+// Testing
+// #######
+
+#[test]
+fn check_contact_overlap() {
+    assert_eq!(contact_overlap(0.5), 0.);
+    assert_eq!(contact_overlap(0.), 0.);
+    assert_eq!(contact_overlap(-0.2), 0.2);
+}
+
+#[test]
+fn check_overlapping_agents_body_compression() {
+    let config = SocialForcesModelConfiguration::default();
+
+    // Agents with a radius of 0.3m, 0.4m apart along the x axis, overlapping by 0.2m
+    let offset = Vec2::new(0.4, 0.);
+
+    let direct = agent_interaction_force(&config, false, offset, Vec2::ZERO, 0.6);
+    let contact = agent_interaction_force(&config, true, offset, Vec2::ZERO, 0.6);
+
+    let expected_repulsion = config.a * (0.2 / config.b).exp();
+    let expected_compression = config.k * 0.2;
+
+    assert!((direct.x - expected_repulsion).abs() / expected_repulsion < 1e-5);
+    assert!((contact.x - expected_repulsion - expected_compression).abs() / expected_repulsion < 1e-5);
+    assert_eq!(contact.y, 0.);
+}
+
+#[test]
+fn check_overlapping_agents_sliding_friction() {
+    let config = SocialForcesModelConfiguration::default();
+
+    let offset = Vec2::new(0.4, 0.);
+
+    // The neighbour moves along the tangent t = (0, 1) relative to the agent
+    let friction = agent_interaction_force(&config, true, offset, Vec2::new(0., 1.), 0.6)
+        - agent_interaction_force(&config, true, offset, Vec2::ZERO, 0.6);
+
+    assert!(friction.x.abs() < 1e-3);
+    assert!((friction.y - config.kappa * 0.2).abs() < 1e-1);
+
+    // No friction without contact
+    let far_offset = Vec2::new(1., 0.);
+    let far_friction = agent_interaction_force(&config, true, far_offset, Vec2::new(0., 1.), 0.6)
+        - agent_interaction_force(&config, true, far_offset, Vec2::ZERO, 0.6);
+
+    assert_eq!(far_friction, Vec2::ZERO);
+}
+
+#[test]
+fn check_overlapping_agents_forces_are_opposite() {
+
+    // Setup
+
+    let mut app = App::new();
+
+    app.insert_resource(SocialForcesModelConfiguration {
+        forces: ForceConfiguration {
+            repulsion_force: RepulsionForceComputationStrategy::DirectWithContact,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    app.add_systems(Update, compute_repulsive_forces);
+
+    let world = app.world_mut();
+
+    let agent_1 = world
+        .spawn((Agent, RepulsiveForce::default(), Position::from(Vec2::ZERO), Speed::new(Vec2::new(1., 0.)), Shape::Circle(0.3)))
+        .id();
+
+    let agent_2 = world
+        .spawn((Agent, RepulsiveForce::default(), Position::from(Vec2::new(0.3, 0.3)), Speed::new(Vec2::new(0., 1.)), Shape::Circle(0.3)))
+        .id();
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    let force_1 = app.world().get::<RepulsiveForce>(agent_1).unwrap().0;
+    let force_2 = app.world().get::<RepulsiveForce>(agent_2).unwrap().0;
+
+    assert_eq!(force_1, -force_2);
+    assert!(force_1.dot(Vec2::new(-1., -1.)) > 0.);
+}
+
+#[test]
+fn check_agent_overlapping_obstacle_is_pushed_out() {
+
+    // Setup
+
+    let mut app = App::new();
+
+    app.insert_resource(SocialForcesModelConfiguration {
+        forces: ForceConfiguration {
+            obstacle_force: ObstacleForceComputationStrategy::DirectWithContact,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    app.add_systems(Update, compute_obstacle_force);
+
+    let world = app.world_mut();
+
+    world.spawn((Obstacle, Position::from(Vec2::ZERO), Shape::Circle(1.)));
+
+    let agent = world
+        .spawn((Agent, ObstacleForce::default(), Position::from(Vec2::new(1.2, 0.)), Speed::new(Vec2::new(0., 1.)), Shape::Circle(0.3)))
+        .id();
+
+    // Act
+
+    app.update();
+
+    // Assert
+
+    let config = SocialForcesModelConfiguration::default();
+    let force = app.world().get::<ObstacleForce>(agent).unwrap().0;
+
+    let expected_pushing = config.a * (0.1 / config.b).exp() + config.k * 0.1;
+    let expected_sliding = -config.kappa * 0.1;
+
+    assert!((force.x - expected_pushing).abs() / expected_pushing < 1e-5);
+    assert!((force.y - expected_sliding).abs() / expected_sliding.abs() < 1e-5);
+}
+
+#[test]
+fn check_spatial_hash_matches_direct_repulsion_within_cutoff() {
+
+    // Setup
+
+    let positions = [Vec2::new(0., 0.), Vec2::new(0.8, 0.1), Vec2::new(0.2, 1.), Vec2::new(1.1, 0.9), Vec2::new(10., 10.)];
+
+    let run = |strategy: RepulsionForceComputationStrategy| {
+        let mut app = App::new();
+
+        app.insert_resource(SocialForcesModelConfiguration {
+            repulsion_cutoff: 5.,
+            forces: ForceConfiguration {
+                repulsion_force: strategy,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        match strategy {
+            RepulsionForceComputationStrategy::SpatialHash => {
+                app.insert_resource(AgentSpatialHash::new(5.));
+                app.add_systems(Update, (rebuild_agent_spatial_hash, compute_repulsive_forces_via_spatial_hash).chain());
+            }
+            _ => {
+                app.add_systems(Update, compute_repulsive_forces);
+            }
+        }
+
+        let agents: Vec<Entity> = positions
+            .iter()
+            .map(|p| app.world_mut().spawn((Agent, RepulsiveForce::default(), Position::from(*p), Speed::new(Vec2::ZERO), Shape::Circle(0.3))).id())
+            .collect();
+
+        app.update();
+
+        agents
+            .into_iter()
+            .map(|agent| app.world().get::<RepulsiveForce>(agent).unwrap().0)
+            .collect::<Vec<Vec2>>()
+    };
+
+    // Act
+
+    let direct = run(RepulsionForceComputationStrategy::Direct);
+    let spatial_hash = run(RepulsionForceComputationStrategy::SpatialHash);
+
+    // Assert
+
+    for (direct, spatial_hash) in direct.iter().zip(spatial_hash.iter()).take(4) {
+        assert!(direct.distance(*spatial_hash) < 1e-3);
+    }
+
+    assert_eq!(spatial_hash[4], Vec2::ZERO);
+}
+
+#[test]
+fn check_edge_grid_matches_direct_obstacle_force_within_cutoff() {
+
+    // Setup
+
+    let agent_positions = [Vec2::new(0., 0.), Vec2::new(2.6, 1.), Vec2::new(-0.5, 3.), Vec2::new(20., 20.)];
+
+    let run = |strategy: ObstacleForceComputationStrategy| {
+        let mut app = App::new();
+
+        app.insert_resource(SocialForcesModelConfiguration {
+            obstacle_cutoff: 10.,
+            forces: ForceConfiguration {
+                obstacle_force: strategy,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        match strategy {
+            ObstacleForceComputationStrategy::EdgeGrid => {
+                app.insert_resource(ObstacleEdgeGrid::new(2.));
+                app.add_systems(Update, (rebuild_obstacle_edge_grid, compute_obstacle_force_via_edge_grid).chain());
+            }
+            _ => {
+                app.add_systems(Update, compute_obstacle_force);
+            }
+        }
+
+        let world = app.world_mut();
+
+        world.spawn((
+            Obstacle,
+            Position::from(Vec2::new(1., 1.)),
+            Shape::Polygon(vec![Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(1., 2.), Vec2::new(0., 1.)]),
+        ));
+        world.spawn((Obstacle, Position::from(Vec2::new(-2., 1.)), Shape::Circle(0.5)));
+
+        let agents: Vec<Entity> = agent_positions
+            .iter()
+            .map(|p| world.spawn((Agent, ObstacleForce::default(), Position::from(*p), Speed::new(Vec2::ZERO), Shape::Circle(0.3))).id())
+            .collect();
+
+        app.update();
+
+        agents
+            .into_iter()
+            .map(|agent| app.world().get::<ObstacleForce>(agent).unwrap().0)
+            .collect::<Vec<Vec2>>()
+    };
+
+    // Act
+
+    let direct = run(ObstacleForceComputationStrategy::Direct);
+    let edge_grid = run(ObstacleForceComputationStrategy::EdgeGrid);
+
+    // Assert
+
+    for (direct, edge_grid) in direct.iter().zip(edge_grid.iter()).take(3) {
+        assert!(direct.distance(*edge_grid) < 1e-3);
     }
-}
\ No newline at end of file
+
+    assert_eq!(edge_grid[3], Vec2::ZERO);
+}
+
+#[test]
+fn check_polygon_agents_are_repelled_like_their_bounding_circle() {
+
+    // Setup
+
+    let run = |strategy: RepulsionForceComputationStrategy, shape: fn() -> Shape| {
+        let mut app = App::new();
+
+        app.insert_resource(SocialForcesModelConfiguration {
+            repulsion_cutoff: 5.,
+            ..Default::default()
+        });
+
+        match strategy {
+            RepulsionForceComputationStrategy::SpatialHash => {
+                app.insert_resource(AgentSpatialHash::new(5.));
+                app.add_systems(Update, (rebuild_agent_spatial_hash, compute_repulsive_forces_via_spatial_hash).chain());
+            }
+            _ => {
+                app.add_systems(Update, compute_repulsive_forces);
+            }
+        }
+
+        let agent = app.world_mut().spawn((Agent, RepulsiveForce::default(), Position::from(Vec2::ZERO), Speed::new(Vec2::ZERO), shape())).id();
+        app.world_mut().spawn((Agent, RepulsiveForce::default(), Position::from(Vec2::new(0.8, 0.)), Speed::new(Vec2::ZERO), shape()));
+
+        app.update();
+
+        app.world().get::<RepulsiveForce>(agent).unwrap().0
+    };
+
+    let square = || Shape::Polygon(vec![Vec2::new(-0.3, -0.3), Vec2::new(0.3, -0.3), Vec2::new(0.3, 0.3), Vec2::new(-0.3, 0.3)]);
+    let circle = || Shape::Circle(0.3);
+
+    // Act
+
+    let direct = [run(RepulsionForceComputationStrategy::Direct, square), run(RepulsionForceComputationStrategy::Direct, circle)];
+    let spatial_hash = [run(RepulsionForceComputationStrategy::SpatialHash, square), run(RepulsionForceComputationStrategy::SpatialHash, circle)];
+
+    // Assert
+
+    for [square, circle] in [direct, spatial_hash] {
+        assert!(square.x < 0.);
+        assert!(square.distance(circle) < 1e-5);
+    }
+
+    assert!(direct[0].distance(spatial_hash[0]) < 1e-5);
+}
+
+#[test]
+fn check_edge_grid_force_near_the_first_point_of_a_closed_polygon() {
+
+    // Setup
+
+    let run = |strategy: ObstacleForceComputationStrategy| {
+        let mut app = App::new();
+
+        app.insert_resource(SocialForcesModelConfiguration {
+            obstacle_cutoff: 10.,
+            forces: ForceConfiguration {
+                obstacle_force: strategy,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        match strategy {
+            ObstacleForceComputationStrategy::EdgeGrid => {
+                app.insert_resource(ObstacleEdgeGrid::new(2.));
+                app.add_systems(Update, (rebuild_obstacle_edge_grid, compute_obstacle_force_via_edge_grid).chain());
+            }
+            _ => {
+                app.add_systems(Update, compute_obstacle_force);
+            }
+        }
+
+        let world = app.world_mut();
+
+        // Wall of the narrow opening scenario, closed by repeating its first point
+        world.spawn((
+            Obstacle,
+            Position::from(Vec2::new(0., 1.5)),
+            Shape::Polygon(vec![Vec2::new(0., 0.), Vec2::new(2., 0.), Vec2::new(2., 9.), Vec2::new(0., 9.), Vec2::new(0., 0.)]),
+        ));
+
+        let agent = world.spawn((Agent, ObstacleForce::default(), Position::from(Vec2::new(-0.4, 1.)), Speed::new(Vec2::ZERO), Shape::Circle(0.3))).id();
+
+        app.update();
+
+        app.world().get::<ObstacleForce>(agent).unwrap().0
+    };
+
+    // Act
+
+    let direct = run(ObstacleForceComputationStrategy::Direct);
+    let edge_grid = run(ObstacleForceComputationStrategy::EdgeGrid);
+
+    // Assert
+
+    assert!(edge_grid.is_finite());
+    assert!(direct.distance(edge_grid) < 1e-3);
+}
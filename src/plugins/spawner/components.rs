@@ -1,8 +1,15 @@
 use bevy::{ecs::{component::Component, entity::Entity}, math::Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::distribution::ParameterDistribution;
 
 #[derive(Component, Clone, Copy)]
 pub struct Spawner;
 
+/// Simulation time the agent entered the simulation at (s)
+#[derive(Component, Clone, Copy)]
+pub struct SpawnTime(pub f32);
+
 #[derive(Component, Clone, Copy)]
 pub struct SpawnerArea(pub Vec2);
 
@@ -15,4 +22,27 @@ pub struct SpawnerSchedule{
 }
 
 #[derive(Component, Clone, Copy)]
-pub struct SpawnerDestination(pub Entity);
\ No newline at end of file
+pub struct SpawnerDestination(pub Entity);
+
+/// Waypoints given to the spawned agents, starting with the `SpawnerDestination`
+#[derive(Component, Clone)]
+pub struct SpawnerItinerary(pub Vec<Entity>);
+
+/// Radius of the spawned agents when their spawner has no radius distribution (m)
+pub const DEFAULT_AGENT_RADIUS: f32 = 0.3;
+
+/// Distributions the parameters of the spawned agents are drawn from
+///
+/// Missing desired speeds and masses are taken from the `SocialForcesModelConfiguration`
+#[derive(Component, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpawnerAgentParameters {
+    /// (m)
+    pub radius: Option<ParameterDistribution>,
+
+    /// (m/s)
+    pub desired_speed: Option<ParameterDistribution>,
+
+    /// (Kg)
+    pub mass: Option<ParameterDistribution>,
+}
@@ -1,13 +1,15 @@
-use bevy::app::{Plugin, PreUpdate, Update};
+use bevy::{app::{Plugin, PreUpdate}, ecs::schedule::{common_conditions::resource_exists, IntoScheduleConfigs}};
 
-use crate::plugins::spawner::systems::*;
+use crate::{plugins::{display::resources::DisplayConfiguration, simulation_clock::plugin::{SimulationPreUpdate, SimulationUpdate}, spawner::systems::*}, resources::rng::SimulationRng};
 
 pub struct SpawnerPlugin;
 
 impl Plugin for SpawnerPlugin {
     fn build(&self, app: &mut bevy::app::App) {
         app
-        .add_systems(PreUpdate, add_mesh_to_obstacles)
-        .add_systems(Update, spawner);
+        .init_resource::<SimulationRng>()
+        .add_systems(PreUpdate, add_mesh_to_obstacles.run_if(resource_exists::<DisplayConfiguration>))
+        .add_systems(SimulationPreUpdate, add_spawn_time_to_agents)
+        .add_systems(SimulationUpdate, spawner);
     }
 }
@@ -1,17 +1,17 @@
-use bevy::{color::palettes::tailwind::{BLUE_500, YELLOW_100}, diagnostic::FrameCount, prelude::*};
+use bevy::{color::palettes::tailwind::YELLOW_100, prelude::*};
 use rand::Rng;
 
 use crate::{
     components::{
         physics::{Position, Shape, Speed},
-        prelude::{Agent, Destination},
+        prelude::{Agent, Destination, Itinerary},
     },
-    plugins::{display::resources::DisplayConfiguration, spawner::components::*}, resources::configuration::SimulationConfiguration,
+    plugins::{display::resources::DisplayConfiguration, social_foces_model::components::{DesiredSpeed, Mass}, spawner::components::*}, resources::{configuration::SimulationTime, rng::SimulationRng},
 };
 
 pub fn add_mesh_to_obstacles(
     mut commands: Commands,
-    spawners: Query<(Entity, &SpawnerArea), With<Spawner>>,
+    spawners: Query<(Entity, &SpawnerArea), (With<Spawner>, Without<Mesh2d>)>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut meshes: ResMut<Assets<Mesh>>,
     config: Res<DisplayConfiguration>
@@ -31,16 +31,14 @@ pub fn add_mesh_to_obstacles(
 
 pub fn spawner(
     mut commands: Commands,
-    frames: Res<FrameCount>,
-    config: Res<SimulationConfiguration>,
-    mut spawners: Query<(&Position, &SpawnerArea, &mut SpawnerSchedule, &SpawnerDestination), With<Spawner>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    time: Res<SimulationTime>,
+    mut rng: ResMut<SimulationRng>,
+    mut spawners: Query<(&Position, &SpawnerArea, &mut SpawnerSchedule, &SpawnerDestination, Option<&SpawnerAgentParameters>, Option<&SpawnerItinerary>), With<Spawner>>,
 ) {
 
-    let now = frames.0 as f32 * config.simulation_time_step;
-    let mut rng = rand::rng();
+    let now = time.elapsed();
 
-    for (position, area, mut schedule, destination) in spawners.iter_mut() {
+    for (position, area, mut schedule, destination, parameters, itinerary) in spawners.iter_mut() {
 
         if now < schedule.start_time || now > schedule.end_time {
             continue;
@@ -57,13 +55,42 @@ pub fn spawner(
         let x = position.value().x + rng.random_range(-size.x..=size.x);
         let y = position.value().y + rng.random_range(-size.y..=size.y);
 
-        commands.spawn((
+        let mut agent = commands.spawn((
             Agent,
             Position::from(Vec2::new(x, y)),
-            Shape::Circle(0.3),
             Speed::new(Vec2::new(0.0, 0.)),
-            MeshMaterial2d(materials.add(Color::from(BLUE_500))),
             Destination(destination.0),
+            SpawnTime(now),
         ));
+
+        let parameters = parameters.copied().unwrap_or_default();
+
+        let radius = parameters.radius.map_or(DEFAULT_AGENT_RADIUS, |radius| radius.sample(&mut *rng));
+
+        agent.insert(Shape::Circle(radius));
+
+        // Missing parameters are given the model defaults by `add_parameters_to_agents`
+        if let Some(desired_speed) = parameters.desired_speed {
+            agent.insert(DesiredSpeed(desired_speed.sample(&mut *rng)));
+        }
+
+        if let Some(mass) = parameters.mass {
+            agent.insert(Mass(mass.sample(&mut *rng)));
+        }
+
+        if let Some(itinerary) = itinerary {
+            agent.insert(Itinerary::new(itinerary.0.clone()));
+        }
+    }
+}
+
+/// Agents not created by a spawner, e.g. the ones of a scenario, start at the current time
+pub fn add_spawn_time_to_agents(
+    mut commands: Commands,
+    time: Res<SimulationTime>,
+    agents: Query<Entity, (Added<Agent>, Without<SpawnTime>)>,
+) {
+    for entity in agents.iter() {
+        commands.entity(entity).insert(SpawnTime(time.elapsed()));
     }
 }
@@ -10,4 +10,55 @@ impl Default for SimulationConfiguration {
     fn default() -> Self {
         Self { simulation_time_step: 0.2 }
     }
-}
\ No newline at end of file
+}
+
+/// Radius the Voronoi cells of the agents are cut off at, for the measurements and the Voronoi density fields (m)
+#[derive(Resource, Clone, Copy)]
+pub struct VoronoiCutoff(pub f32);
+
+impl Default for VoronoiCutoff {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+/// Simulation clock, advanced by exactly `simulation_time_step` on each simulation tick
+#[derive(Resource, Default, Clone, Copy)]
+pub struct SimulationTime {
+    elapsed: f32,
+    ticks: u32,
+}
+
+impl SimulationTime {
+    /// Simulated time since the start of the simulation (s)
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Number of simulation ticks run so far
+    pub fn ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    pub fn advance(&mut self, time_step: f32) {
+        self.ticks += 1;
+        self.elapsed = self.ticks as f32 * time_step;
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SimulationMode {
+    /// Ticks are run so that simulated time follows wall-clock time
+    #[default]
+    RealTime,
+
+    /// One tick is run on every app update, regardless of wall-clock time
+    AsFastAsPossible,
+
+    /// No ticks are run, except the ones requested through `SimulationStep`
+    Paused,
+}
+
+/// Number of single ticks requested while the simulation is paused
+#[derive(Resource, Default)]
+pub struct SimulationStep(pub u32);
@@ -1 +1,2 @@
-pub mod configuration;
\ No newline at end of file
+pub mod configuration;
+pub mod rng;
\ No newline at end of file
@@ -0,0 +1,125 @@
+use bevy::ecs::resource::Resource;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Random number generator shared by every stochastic system, two runs with the same seed are identical
+#[derive(Resource)]
+pub struct SimulationRng {
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+impl SimulationRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+/// Seeded at random, for plugins used without a scenario, the `ScenarioLoaderPlugin` replaces it with the scenario seed
+impl Default for SimulationRng {
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+impl RngCore for SimulationRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        self.rng.fill_bytes(dst)
+    }
+}
+
+// #######
+// Testing
+// #######
+
+#[test]
+fn check_spawner_and_objective_plugins_run_without_a_scenario() {
+    use bevy::{app::App, math::Vec2};
+
+    use crate::{
+        components::{physics::{Position, Shape}, prelude::{Agent, Objective}},
+        plugins::{
+            simple_objective::plugin::SimpleObjective,
+            simulation_clock::plugin::SimulationClockPlugin,
+            spawner::{components::*, plugin::SpawnerPlugin},
+        },
+        resources::configuration::{SimulationConfiguration, SimulationMode},
+    };
+
+    // Setup
+
+    let mut app = App::new();
+
+    app.add_plugins(SimulationClockPlugin { mode: SimulationMode::AsFastAsPossible })
+        .add_plugins((SpawnerPlugin, SimpleObjective::default()))
+        .init_resource::<SimulationConfiguration>();
+
+    let objective = app.world_mut().spawn((Objective, Position::from(Vec2::new(10., 0.)), Shape::Circle(1.))).id();
+
+    app.world_mut().spawn((
+        Spawner,
+        Position::from(Vec2::ZERO),
+        SpawnerArea(Vec2::splat(1.)),
+        SpawnerSchedule { interval: 1., last_spawn: -1., start_time: 0., end_time: 10. },
+        SpawnerDestination(objective),
+    ));
+
+    // Act
+
+    for _ in 0..10 {
+        app.update();
+    }
+
+    // Assert
+
+    let world = app.world_mut();
+
+    assert!(world.query::<&Agent>().iter(world).count() > 0);
+}
+
+#[test]
+fn check_scenario_seed_replaces_the_default_rng() {
+    use bevy::{app::App, state::app::StatesPlugin};
+
+    use crate::{
+        plugins::{scenario_loader::plugin::ScenarioLoaderPlugin, simple_objective::plugin::SimpleObjective, spawner::plugin::SpawnerPlugin},
+        scenarios::built_in::BuiltInScenario,
+    };
+
+    // Setup
+
+    let mut scenario = BuiltInScenario::NarrowOpening.load().unwrap();
+    scenario.seed = Some(42);
+
+    let mut plugins_first = App::new();
+    let mut scenario_first = App::new();
+
+    // Act
+
+    plugins_first.add_plugins(StatesPlugin)
+        .add_plugins((SpawnerPlugin, SimpleObjective::default()))
+        .add_plugins(ScenarioLoaderPlugin { scenario: scenario.clone() });
+
+    scenario_first.add_plugins(StatesPlugin)
+        .add_plugins(ScenarioLoaderPlugin { scenario });
+
+    // Assert
+
+    assert_eq!(plugins_first.world().resource::<SimulationRng>().seed(), 42);
+    assert_eq!(scenario_first.world().resource::<SimulationRng>().seed(), 42);
+}
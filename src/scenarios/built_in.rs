@@ -0,0 +1,21 @@
+use clap::ValueEnum;
+
+use crate::plugins::scenario_loader::models::Scenario;
+
+/// Scenarios shipped with the binary, their descriptions live in the `scenarios` directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BuiltInScenario {
+    NarrowOpening,
+    Corridor,
+}
+
+impl BuiltInScenario {
+    pub fn load(&self) -> anyhow::Result<Scenario> {
+        let content = match self {
+            BuiltInScenario::NarrowOpening => include_str!("../../scenarios/narrow_opening.ron"),
+            BuiltInScenario::Corridor => include_str!("../../scenarios/corridor.ron"),
+        };
+
+        Scenario::from_ron(content)
+    }
+}
@@ -1 +1,2 @@
+pub mod built_in;
 pub mod example;
\ No newline at end of file
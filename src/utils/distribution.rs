@@ -0,0 +1,88 @@
+use anyhow::ensure;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+
+/// Distribution a scalar parameter is drawn from, e.g. the desired speed of spawned agents
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ParameterDistribution {
+    Constant(f32),
+    Uniform { min: f32, max: f32 },
+
+    /// Normal distribution, samples are clamped to `[min, max]`
+    Normal { mean: f32, std_dev: f32, min: f32, max: f32 },
+}
+
+impl ParameterDistribution {
+    /// Fails when `sample` could not draw from the distribution, e.g. a negative standard deviation or `min > max`
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match *self {
+            ParameterDistribution::Constant(value) => {
+                ensure!(value.is_finite(), "Constant value must be finite")
+            }
+            ParameterDistribution::Uniform { min, max } => {
+                ensure!(min.is_finite() && max.is_finite(), "Uniform bounds must be finite");
+                ensure!(min <= max, "Uniform min must not be greater than max");
+            }
+            ParameterDistribution::Normal { mean, std_dev, min, max } => {
+                ensure!(mean.is_finite(), "Normal mean must be finite");
+                ensure!(std_dev.is_finite() && std_dev >= 0., "Normal standard deviation must be finite and not negative");
+                ensure!(!min.is_nan() && !max.is_nan() && min <= max, "Normal min must not be greater than max");
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f32 {
+        match *self {
+            ParameterDistribution::Constant(value) => value,
+            ParameterDistribution::Uniform { min, max } => rng.random_range(min..=max),
+            ParameterDistribution::Normal { mean, std_dev, min, max } => {
+                let normal = Normal::new(mean, std_dev).expect("Standard deviation must be finite and positive");
+                normal.sample(rng).clamp(min, max)
+            }
+        }
+    }
+}
+
+// #######
+// Testing
+// #######
+
+#[test]
+fn check_normal_samples_are_clamped() {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+    let distribution = ParameterDistribution::Normal { mean: 1.34, std_dev: 0.26, min: 1., max: 1.5 };
+    let samples: Vec<f32> = (0..1000).map(|_| distribution.sample(&mut rng)).collect();
+
+    let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+
+    assert!(samples.iter().all(|s| (1. ..=1.5).contains(s)));
+    assert!((mean - 1.3).abs() < 0.1);
+}
+
+#[test]
+fn check_invalid_distributions_are_rejected() {
+    let valid = [
+        ParameterDistribution::Constant(1.),
+        ParameterDistribution::Uniform { min: 1., max: 1. },
+        ParameterDistribution::Normal { mean: 1.34, std_dev: 0., min: 0.5, max: 2. },
+    ];
+
+    let invalid = [
+        ParameterDistribution::Constant(f32::NAN),
+        ParameterDistribution::Uniform { min: 2., max: 1. },
+        ParameterDistribution::Uniform { min: 0., max: f32::INFINITY },
+        ParameterDistribution::Normal { mean: 1.34, std_dev: -0.26, min: 0.5, max: 2. },
+        ParameterDistribution::Normal { mean: 1.34, std_dev: f32::NAN, min: 0.5, max: 2. },
+        ParameterDistribution::Normal { mean: 1.34, std_dev: 0.26, min: 2., max: 0.5 },
+    ];
+
+    assert!(valid.iter().all(|d| d.validate().is_ok()));
+    assert!(invalid.iter().all(|d| d.validate().is_err()));
+}